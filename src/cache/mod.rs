@@ -0,0 +1,223 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Files that a built output depends on
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dependencies {
+    /// Input files
+    pub inputs: Vec<PathBuf>,
+    /// Does the output depend on generator state (config, plugins, data)?
+    pub generator: bool,
+    /// Does the output depend on the site index (`site.pages`)?
+    pub site_index: bool,
+}
+
+impl Dependencies {
+    /// Dependencies of an output, that only depends on it's source
+    pub fn source(path: &Path) -> Self {
+        Self {
+            inputs: vec![path.to_owned()],
+            generator: false,
+            site_index: false,
+        }
+    }
+}
+
+/// Fingerprints of the generator state, that outputs can depend on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Fingerprints {
+    /// See [`OreStaty::generator_fingerprint`]
+    pub generator: u64,
+    /// See [`OreStaty::site_index_fingerprint`]
+    pub site_index: u64,
+}
+
+/// Cached information about one built output
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Content hashes of input files
+    pub inputs: BTreeMap<PathBuf, u64>,
+    /// Generator fingerprint the output was built with, if it depends on it
+    pub generator: Option<u64>,
+    /// Site index fingerprint the output was built with, if it depends on it
+    #[serde(default)]
+    pub site_index: Option<u64>,
+}
+
+/// Persistent build cache, used to skip outputs whose inputs did not change
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildCache {
+    /// Cache directory
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// Built outputs
+    pub outputs: BTreeMap<PathBuf, CacheEntry>,
+}
+
+impl BuildCache {
+    const FILE: &'static str = "cache.json";
+
+    /// Load cache from the directory. Missing or invalid cache is treated as empty
+    pub fn load(path: &Path) -> Self {
+        let cache = std::fs::read_to_string(path.join(Self::FILE))
+            .ok()
+            .and_then(|cache| serde_json::from_str::<Self>(&cache).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_owned(),
+            ..cache
+        }
+    }
+
+    /// Save cache into it's directory
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.path)?;
+        std::fs::write(self.path.join(Self::FILE), serde_json::to_string(self)?)
+    }
+
+    /// Check if the output exists and none of it's dependencies changed since it was built
+    pub fn is_fresh(&self, output: &Path, fingerprints: Fingerprints) -> bool {
+        let Some(entry) = self.outputs.get(output) else {
+            return false;
+        };
+        output.is_file()
+            && entry
+                .generator
+                .is_none_or(|hash| hash == fingerprints.generator)
+            && entry
+                .site_index
+                .is_none_or(|hash| hash == fingerprints.site_index)
+            && entry
+                .inputs
+                .iter()
+                .all(|(input, hash)| hash_file(input) == Some(*hash))
+    }
+
+    /// Record a successfully built output
+    pub fn insert(
+        &mut self,
+        output: PathBuf,
+        dependencies: Dependencies,
+        fingerprints: Fingerprints,
+    ) {
+        let inputs = dependencies
+            .inputs
+            .into_iter()
+            .filter_map(|input| hash_file(&input).map(|hash| (input, hash)))
+            .collect();
+        self.outputs.insert(
+            output,
+            CacheEntry {
+                inputs,
+                generator: dependencies.generator.then_some(fingerprints.generator),
+                site_index: dependencies.site_index.then_some(fingerprints.site_index),
+            },
+        );
+    }
+
+    /// Forget about an output, so that it gets rebuilt next time
+    pub fn remove(&mut self, output: &Path) {
+        self.outputs.remove(output);
+    }
+}
+
+/// Hash any value
+pub fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash contents of a file, [`None`] if it can't be read
+pub fn hash_file(path: &Path) -> Option<u64> {
+    std::fs::read(path).ok().map(|content| hash(&content))
+}
+
+impl OreStaty<'_> {
+    /// Fingerprint of everything besides sources and the site index, that affects built pages:
+    /// config (including site information), plugin files, data and OreStaty version
+    pub fn generator_fingerprint(&self) -> u64 {
+        let mut hasher = std::hash::DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        serde_json::to_string(&self.config)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        serde_json::to_string(&self.data)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        for plugin in &self.plugins {
+            plugin.hash(&mut hasher);
+            hash_file(plugin).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Fingerprint of the site index (`site.pages`). It changes with front matter, URL or
+    /// summary of any page
+    pub fn site_index_fingerprint(&self) -> u64 {
+        hash(&serde_json::to_string(&self.site.pages).unwrap_or_default())
+    }
+
+    /// Get both fingerprints, see [`Fingerprints`]
+    pub fn fingerprints(&self) -> Fingerprints {
+        Fingerprints {
+            generator: self.generator_fingerprint(),
+            site_index: self.site_index_fingerprint(),
+        }
+    }
+
+    /// Check if a page might read the site index: if it's source or any template loaded from
+    /// a file mentions `pages` (like `site.pages`). Templates registered directly in
+    /// [`OreStaty::handlebars`] are not checked
+    pub fn uses_site_index(&self, src: &Path) -> bool {
+        self.templates
+            .values()
+            .any(|template| template.uses_site_index)
+            || std::fs::read_to_string(src).map_or(true, |source| source.contains("pages"))
+    }
+
+    /// Dependencies of a page, built with Handlebars: it's source, generator state and the
+    /// site index, if the page uses it (see [`Self::uses_site_index`])
+    pub fn page_dependencies(&self, src: &Path) -> Dependencies {
+        Dependencies {
+            generator: true,
+            site_index: self.uses_site_index(src),
+            ..Dependencies::source(src)
+        }
+    }
+}
+
+/// SASS file system, that records all files read during compilation
+#[derive(Debug, Default)]
+pub struct DependencyTracker(std::sync::Mutex<Vec<PathBuf>>);
+
+impl DependencyTracker {
+    /// Get all files that were read
+    pub fn into_inputs(self) -> Vec<PathBuf> {
+        self.0.into_inner().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl grass::Fs for DependencyTracker {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        if let Ok(mut inputs) = self.0.lock() {
+            inputs.push(path.to_owned());
+        }
+        std::fs::read(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+}
@@ -0,0 +1,121 @@
+use super::*;
+use serde_json::{Map, Value};
+
+impl OreStaty<'_> {
+    /// Load all data files (`.toml`, `.json`, `.yaml`/`.yml` and `.csv`) from the directory,
+    /// replacing previously loaded data. Every file becomes a field of `data` in templates,
+    /// named after the file: `authors.toml` becomes `data.authors`, `team/members.csv` becomes
+    /// `data.team.members`. CSV files become arrays of rows, each row is an object with
+    /// columns named by the header
+    pub fn load_data(&mut self, path: &Path) {
+        self.data = self.load_data_dir(path);
+    }
+
+    fn load_data_dir(&self, path: &Path) -> Map<String, Value> {
+        let files = match std::fs::read_dir(path) {
+            Ok(files) => files,
+            Err(err) => {
+                self.report(Diagnostic::io("Failed to read data directory", err).in_file(path));
+                return Map::new();
+            }
+        };
+
+        let mut data = Map::new();
+        for file in files {
+            let file = match file {
+                Ok(file) => file.path(),
+                Err(err) => {
+                    self.report(Diagnostic::io("Failed to read file", err).in_file(path));
+                    continue;
+                }
+            };
+            let Some(name) = file
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let value = if file.is_dir() {
+                Value::Object(self.load_data_dir(&file))
+            } else {
+                match self.unwrap_or_report(
+                    load_data_file(&file).map_err(|diagnostic| diagnostic.or_in_file(&file)),
+                ) {
+                    Some(value) => value,
+                    None => continue,
+                }
+            };
+            if data.insert(name.clone(), value).is_some() {
+                self.report(
+                    Diagnostic::warning(
+                        diagnostic::Code::Data,
+                        format!("Data {:?} is defined by multiple files", name),
+                    )
+                    .in_file(&file),
+                );
+            }
+        }
+        data
+    }
+
+    /// Get data loaded from data files, see [`Self::load_data`]
+    pub fn data(&self) -> &Map<String, Value> {
+        &self.data
+    }
+}
+
+/// Parse a data file into JSON value
+pub fn load_data_file(path: &Path) -> BuildResult<Value> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| Diagnostic::io("Failed to read data file", err))?;
+    let invalid =
+        |diagnostic: Diagnostic| diagnostic.context(diagnostic::Code::Data, "Invalid data file");
+    match path
+        .extension()
+        .map_or(String::new(), |ext| ext.to_string_lossy().to_lowercase())
+        .as_str()
+    {
+        "json" => serde_json::from_str(&source).map_err(|err| invalid(Diagnostic::from_json(&err))),
+        "toml" => {
+            toml::from_str(&source).map_err(|err| invalid(Diagnostic::from_toml(&err, &source)))
+        }
+        "yaml" | "yml" => {
+            serde_yml::from_str(&source).map_err(|err| invalid(Diagnostic::from_yaml(&err)))
+        }
+        "csv" => {
+            let csv_error = |err: csv::Error| {
+                let diagnostic = Diagnostic::error(diagnostic::Code::Data, err.to_string());
+                invalid(match err.position() {
+                    Some(position) => diagnostic.at(position.line() as usize, 1),
+                    None => diagnostic,
+                })
+            };
+            let mut reader = csv::Reader::from_reader(source.as_bytes());
+            let headers = reader.headers().map_err(csv_error)?.clone();
+            reader
+                .records()
+                .map(|record| {
+                    let record = record.map_err(csv_error)?;
+                    Ok(Value::Object(
+                        headers
+                            .iter()
+                            .zip(record.iter())
+                            .map(|(header, field)| {
+                                (header.to_owned(), Value::String(field.to_owned()))
+                            })
+                            .collect(),
+                    ))
+                })
+                .collect::<BuildResult<Vec<_>>>()
+                .map(Value::Array)
+        }
+        ext => Err(Diagnostic::warning(
+            diagnostic::Code::Data,
+            format!("Unknown data file extension {:?}, skipping", ext),
+        )),
+    }
+}
@@ -0,0 +1,244 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Severity of a [`Diagnostic`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Something looks wrong, but the output is still built
+    Warning,
+    /// The output could not be built
+    Error,
+}
+
+/// Kind of a [`Diagnostic`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Code {
+    /// Failed to read or write a file
+    Io,
+    /// Source file type is not known
+    UnknownSource,
+    /// Invalid front matter
+    Metadata,
+    /// Invalid Handlebars template
+    Template,
+    /// Failed to render Handlebars template
+    Render,
+    /// Failed to compile SASS
+    Sass,
+    /// Failed to load a plugin
+    Plugin,
+    /// Invalid config
+    Config,
+    /// Invalid data file
+    Data,
+}
+
+impl Code {
+    /// Code as a string, as used in messages
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::UnknownSource => "unknown-source",
+            Self::Metadata => "metadata",
+            Self::Template => "template",
+            Self::Render => "render",
+            Self::Sass => "sass",
+            Self::Plugin => "plugin",
+            Self::Config => "config",
+            Self::Data => "data",
+        }
+    }
+}
+
+/// Build error or warning, with the location where it happened
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Diagnostic {
+    /// Severity
+    pub severity: Severity,
+    /// Kind of the diagnostic
+    pub code: Code,
+    /// Human readable message
+    pub message: String,
+    /// File the diagnostic is about
+    pub file: Option<PathBuf>,
+    /// Line in the file, starting from 1
+    pub line: Option<usize>,
+    /// Column in the line, starting from 1
+    pub column: Option<usize>,
+}
+
+/// Result of building a single output
+pub type BuildResult<T> = Result<T, Diagnostic>;
+
+impl Diagnostic {
+    /// Create an error without location
+    pub fn error(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Create a warning without location
+    pub fn warning(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Create an IO error with the message describing what failed
+    pub fn io(message: impl std::fmt::Display, err: std::io::Error) -> Self {
+        Self::error(Code::Io, format!("{}: {}", message, err))
+    }
+
+    /// Set the file the diagnostic is about
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_owned());
+        self
+    }
+
+    /// Set the file the diagnostic is about, if it's not set yet
+    pub fn or_in_file(self, file: &Path) -> Self {
+        if self.file.is_some() {
+            self
+        } else {
+            self.in_file(file)
+        }
+    }
+
+    /// Set position in the file
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// Shift the line, for example if the diagnostic is about a block, that starts not at the
+    /// beginning of the file
+    pub fn offset_lines(mut self, lines: usize) -> Self {
+        self.line = self.line.map(|line| line + lines);
+        self
+    }
+
+    /// Is this an error (and not a warning)?
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Create an error from a Handlebars template error
+    pub fn from_template(err: &handlebars::TemplateError) -> Self {
+        let diagnostic = Self::error(Code::Template, err.reason().to_string());
+        match err.pos() {
+            Some((line, column)) => diagnostic.at(line, column),
+            None => diagnostic,
+        }
+    }
+
+    /// Create an error from a Handlebars render error
+    pub fn from_render(err: &handlebars::RenderError) -> Self {
+        if let handlebars::RenderErrorReason::TemplateError(err) = err.reason() {
+            return Self::from_template(err);
+        }
+        let diagnostic = Self::error(Code::Render, err.reason().to_string());
+        match (err.line_no, err.column_no) {
+            (Some(line), Some(column)) => diagnostic.at(line, column),
+            _ => diagnostic,
+        }
+    }
+
+    /// Set the code and prefix the message with context, like "Invalid metadata"
+    pub fn context(mut self, code: Code, context: &str) -> Self {
+        self.code = code;
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+
+    /// Create an error from a YAML error
+    pub fn from_yaml(err: &serde_yml::Error) -> Self {
+        let message = err.to_string();
+        match err.location() {
+            Some(location) => {
+                let (line, column) = (location.line(), location.column());
+                let suffix = format!(" at line {} column {}", line, column);
+                Self::error(
+                    Code::Metadata,
+                    message.strip_suffix(&suffix).unwrap_or(&message),
+                )
+                .at(line, column)
+            }
+            None => Self::error(Code::Metadata, message),
+        }
+    }
+
+    /// Create an error from a JSON error
+    pub fn from_json(err: &serde_json::Error) -> Self {
+        let message = err.to_string();
+        let (line, column) = (err.line(), err.column());
+        let suffix = format!(" at line {} column {}", line, column);
+        let diagnostic = Self::error(
+            Code::Metadata,
+            message.strip_suffix(&suffix).unwrap_or(&message),
+        );
+        match line {
+            0 => diagnostic,
+            _ => diagnostic.at(line, column),
+        }
+    }
+
+    /// Create an error from a SASS error
+    pub fn from_sass(err: grass::Error) -> Self {
+        match err.kind() {
+            grass::ErrorKind::ParseError { message, loc, .. } => Self::error(Code::Sass, message)
+                .in_file(Path::new(loc.file.name()))
+                .at(loc.begin.line + 1, loc.begin.column + 1),
+            grass::ErrorKind::IoError(err) => Self::error(Code::Io, err.to_string()),
+            grass::ErrorKind::FromUtf8Error(err) => Self::error(Code::Sass, err),
+            _ => Self::error(Code::Sass, "Unknown SASS error"),
+        }
+    }
+
+    /// Create an error from a TOML error. `source` is the parsed TOML, used to find the
+    /// position
+    pub fn from_toml(err: &toml::de::Error, source: &str) -> Self {
+        let message = err.message().trim().replace('\n', " ");
+        let diagnostic = Self::error(Code::Config, message);
+        match err.span() {
+            Some(span) => {
+                let before = &source[..span.start.min(source.len())];
+                let line = before.matches('\n').count() + 1;
+                let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+                diagnostic.at(line, column)
+            }
+            None => diagnostic,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning[{}]", self.code.as_str())?,
+            Severity::Error => write!(f, "error[{}]", self.code.as_str())?,
+        }
+        if let Some(file) = &self.file {
+            write!(f, " {}", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(column) = self.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+            write!(f, ":")?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
@@ -0,0 +1,576 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Feed settings, see [`Config::feeds`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedConfig {
+    /// Generate RSS 2.0 feed, `feed.xml`
+    #[serde(default = "default_true")]
+    pub rss: bool,
+    /// Generate Atom feed, `atom.xml`
+    #[serde(default = "default_true")]
+    pub atom: bool,
+    /// Generate JSON Feed, `feed.json`
+    #[serde(default = "default_true")]
+    pub json: bool,
+    /// Sections (directories in "src") that get their own feeds, like `blog/feed.xml`
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Generate feeds for every tag (front matter `tags`), like `tags/rust/feed.xml`
+    #[serde(default)]
+    pub tags: bool,
+    /// Put the whole page content into feed items, instead of the summary
+    #[serde(default)]
+    pub full_content: bool,
+    /// Maximum number of items in a feed, 0 for no limit
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Author of the items, if page front matter doesn't have `author`
+    pub author: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            rss: true,
+            atom: true,
+            json: true,
+            sections: Vec::new(),
+            tags: false,
+            full_content: false,
+            limit: default_limit(),
+            author: None,
+        }
+    }
+}
+
+/// Feed, ready to be written in any format
+struct Feed<'a> {
+    /// Feed title
+    title: String,
+    /// Absolute URL of the directory with the feed files
+    url: String,
+    items: Vec<&'a Item<'a>>,
+}
+
+/// Writes a feed in one of the formats
+type Writer = fn(&site::Site, &Feed) -> String;
+
+/// Pages that get into a feed
+enum Filter<'a> {
+    All,
+    Section(&'a Path),
+    /// Tags with this slug
+    Tag(String),
+}
+
+/// Item of a feed
+struct Item<'a> {
+    page: &'a site::PageEntry,
+    /// Absolute URL of the page
+    url: String,
+    date: chrono::DateTime<chrono::FixedOffset>,
+    /// HTML with absolute links
+    content: Option<String>,
+    author: Option<&'a str>,
+    tags: Vec<&'a str>,
+}
+
+impl OreStaty<'_> {
+    /// Generate feeds of dated pages, newest first, if [`Config::feeds`] is set. Feeds are
+    /// generated on every build, so pages that were not rebuilt are still up to date in them
+    pub fn build_feeds(&mut self, sources: &[site::Source], dst: &Path) {
+        let Some(config) = &self.config.feeds else {
+            return;
+        };
+        let Some(base_url) = &self.config.base_url else {
+            self.report(Diagnostic::error(
+                diagnostic::Code::Config,
+                "Feeds need base_url to be set",
+            ));
+            return;
+        };
+        let base_url = base_url.trim_end_matches('/');
+        let sources = sources
+            .iter()
+            .map(|source| (&source.relative_path, source))
+            .collect::<HashMap<_, _>>();
+
+        let mut items = self
+            .site
+            .pages
+            .iter()
+            .filter_map(|page| {
+                let date = page.metadata.date.as_deref().and_then(text::parse_date)?;
+                let date = date.and_local_timezone(chrono::Local).earliest()?;
+                let url = format!("{}{}", base_url, page.url);
+                let content = match config.full_content {
+                    true => sources.get(&page.path).and_then(|source| {
+                        let content = self
+                            .processors
+                            .get(&source.processor)
+                            .ok_or_else(|| {
+                                Diagnostic::error(
+                                    diagnostic::Code::UnknownSource,
+                                    format!("Unknown processor {:?}", source.processor),
+                                )
+                            })
+                            .and_then(|processor| processor.content(self, source));
+                        // Errors are reported when the page is built
+                        content.ok()
+                    }),
+                    false => None,
+                }
+                .or_else(|| page.info.summary_html.clone())
+                .map(|content| absolute_links(&content, base_url, &page.url));
+                Some(Item {
+                    page,
+                    url,
+                    date: date.fixed_offset(),
+                    content,
+                    author: page
+                        .metadata
+                        .extra
+                        .get("author")
+                        .and_then(|author| author.as_str())
+                        .or(config.author.as_deref()),
+                    tags: page
+                        .metadata
+                        .extra
+                        .get("tags")
+                        .and_then(|tags| tags.as_array())
+                        .map_or(Vec::new(), |tags| {
+                            tags.iter().filter_map(|tag| tag.as_str()).collect()
+                        }),
+                })
+            })
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| std::cmp::Reverse(item.date));
+
+        let title = self.site.title.clone().unwrap_or_default();
+        let mut feeds = vec![(PathBuf::new(), title.clone(), Filter::All)];
+        for section in &config.sections {
+            let section = section.trim_matches('/');
+            feeds.push((
+                PathBuf::from(section),
+                format!("{} - {}", title, section),
+                Filter::Section(Path::new(section)),
+            ));
+        }
+        if config.tags {
+            let (tags, skipped) =
+                group_tags(items.iter().flat_map(|item| item.tags.iter().copied()));
+            for tag in skipped {
+                self.report(Diagnostic::warning(
+                    diagnostic::Code::Metadata,
+                    format!(
+                        "Tag {:?} has no letters or digits to name its feed, skipping it",
+                        tag
+                    ),
+                ));
+            }
+            for (slug, tag) in tags {
+                feeds.push((
+                    Path::new("tags").join(&slug),
+                    format!("{} - {}", title, tag),
+                    Filter::Tag(slug),
+                ));
+            }
+        }
+
+        let mut written = Vec::new();
+        for (directory, title, filter) in feeds {
+            let feed_items = items.iter().filter(|item| match &filter {
+                Filter::All => true,
+                Filter::Section(section) => item.page.path.starts_with(section),
+                Filter::Tag(slug) => item.tags.iter().any(|tag| text::slugify(tag) == *slug),
+            });
+            let feed = Feed {
+                title,
+                url: format!("{}{}", base_url, directory_url(&directory)),
+                items: match config.limit {
+                    0 => feed_items.collect(),
+                    limit => feed_items.take(limit).collect(),
+                },
+            };
+            let formats: [(bool, &str, Writer); 3] = [
+                (config.rss, "feed.xml", rss),
+                (config.atom, "atom.xml", atom),
+                (config.json, "feed.json", json_feed),
+            ];
+            for (enabled, file, format) in formats {
+                if !enabled {
+                    continue;
+                }
+                let output = dst.join(&directory).join(file);
+                if let Err(diagnostic) = self.write_file(&format(&self.site, &feed), &output) {
+                    self.report(diagnostic);
+                }
+                written.push(output);
+            }
+        }
+        self.manifest.files.extend(written);
+    }
+}
+
+/// Group tags by their slug (so `Rust` and `rust` share `tags/rust/`), naming each group after
+/// it's first spelling. Also returns tags without a slug, that can't have a feed
+fn group_tags<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+) -> (BTreeMap<String, &'a str>, BTreeSet<&'a str>) {
+    let mut groups = BTreeMap::new();
+    let mut skipped = BTreeSet::new();
+    for tag in tags.into_iter().collect::<BTreeSet<_>>() {
+        match text::slugify(tag) {
+            slug if slug.is_empty() => {
+                skipped.insert(tag);
+            }
+            slug => {
+                groups.entry(slug).or_insert(tag);
+            }
+        }
+    }
+    (groups, skipped)
+}
+
+/// URL of a feed directory relative to the site root, like `/tags/rust/`
+fn directory_url(directory: &Path) -> String {
+    let mut url = String::from("/");
+    for component in directory.components() {
+        url.push_str(&component.as_os_str().to_string_lossy());
+        url.push('/');
+    }
+    url
+}
+
+/// Write feed as RSS 2.0
+fn rss(site: &site::Site, feed: &Feed) -> String {
+    let mut rss = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<channel>\n",
+    );
+    rss.push_str(&format!(
+        "<title>{}</title>\n<link>{}</link>\n",
+        xml_escape(&feed.title),
+        xml_escape(&feed.url)
+    ));
+    rss.push_str(&format!(
+        "<atom:link href=\"{}feed.xml\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        xml_escape(&feed.url)
+    ));
+    rss.push_str(&format!(
+        "<description>{}</description>\n",
+        xml_escape(site.description.as_deref().unwrap_or(&feed.title))
+    ));
+    if let Some(language) = &site.language {
+        rss.push_str(&format!("<language>{}</language>\n", xml_escape(language)));
+    }
+    if let Some(item) = feed.items.first() {
+        rss.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            item.date.to_rfc2822()
+        ));
+    }
+    for item in &feed.items {
+        rss.push_str("<item>\n");
+        rss.push_str(&format!(
+            "<title>{}</title>\n<link>{}</link>\n<guid>{}</guid>\n<pubDate>{}</pubDate>\n",
+            xml_escape(item.page.metadata.title.as_deref().unwrap_or_default()),
+            xml_escape(&item.url),
+            xml_escape(&item.url),
+            item.date.to_rfc2822()
+        ));
+        if let Some(author) = item.author {
+            rss.push_str(&format!(
+                "<dc:creator>{}</dc:creator>\n",
+                xml_escape(author)
+            ));
+        }
+        for tag in &item.tags {
+            rss.push_str(&format!("<category>{}</category>\n", xml_escape(tag)));
+        }
+        if let Some(content) = &item.content {
+            rss.push_str(&format!(
+                "<description>{}</description>\n",
+                xml_escape(content)
+            ));
+        }
+        rss.push_str("</item>\n");
+    }
+    rss.push_str("</channel>\n</rss>\n");
+    rss
+}
+
+/// Write feed as Atom
+fn atom(site: &site::Site, feed: &Feed) -> String {
+    let mut atom = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\"",
+    );
+    if let Some(language) = &site.language {
+        atom.push_str(&format!(" xml:lang=\"{}\"", xml_escape(language)));
+    }
+    atom.push_str(">\n");
+    atom.push_str(&format!(
+        "<title>{}</title>\n<id>{}</id>\n\
+         <link href=\"{}\"/>\n<link href=\"{}atom.xml\" rel=\"self\"/>\n",
+        xml_escape(&feed.title),
+        xml_escape(&feed.url),
+        xml_escape(&feed.url),
+        xml_escape(&feed.url)
+    ));
+    let updated = feed.items.first().map_or_else(
+        || chrono::Local::now().fixed_offset().to_rfc3339(),
+        |item| item.date.to_rfc3339(),
+    );
+    atom.push_str(&format!("<updated>{}</updated>\n", updated));
+    for item in &feed.items {
+        atom.push_str("<entry>\n");
+        atom.push_str(&format!(
+            "<title>{}</title>\n<id>{}</id>\n<link href=\"{}\"/>\n<updated>{}</updated>\n",
+            xml_escape(item.page.metadata.title.as_deref().unwrap_or_default()),
+            xml_escape(&item.url),
+            xml_escape(&item.url),
+            item.date.to_rfc3339()
+        ));
+        if let Some(author) = item.author {
+            atom.push_str(&format!(
+                "<author><name>{}</name></author>\n",
+                xml_escape(author)
+            ));
+        }
+        for tag in &item.tags {
+            atom.push_str(&format!("<category term=\"{}\"/>\n", xml_escape(tag)));
+        }
+        if let Some(summary) = &item.page.info.summary {
+            atom.push_str(&format!("<summary>{}</summary>\n", xml_escape(summary)));
+        }
+        if let Some(content) = &item.content {
+            atom.push_str(&format!(
+                "<content type=\"html\">{}</content>\n",
+                xml_escape(content)
+            ));
+        }
+        atom.push_str("</entry>\n");
+    }
+    atom.push_str("</feed>\n");
+    atom
+}
+
+/// Write feed as JSON Feed 1.1
+fn json_feed(site: &site::Site, feed: &Feed) -> String {
+    let items = feed
+        .items
+        .iter()
+        .map(|item| {
+            let mut json = serde_json::json!({
+                "id": item.url,
+                "url": item.url,
+                "title": item.page.metadata.title,
+                "date_published": item.date.to_rfc3339(),
+            });
+            let Some(json_item) = json.as_object_mut() else {
+                unreachable!("JSON object is not an object! Buggy build!");
+            };
+            if let Some(content) = &item.content {
+                json_item.insert("content_html".to_owned(), content.as_str().into());
+            }
+            if let Some(summary) = &item.page.info.summary {
+                json_item.insert("summary".to_owned(), summary.as_str().into());
+            }
+            if let Some(author) = item.author {
+                json_item.insert(
+                    "authors".to_owned(),
+                    serde_json::json!([{ "name": author }]),
+                );
+            }
+            if !item.tags.is_empty() {
+                json_item.insert("tags".to_owned(), item.tags.clone().into());
+            }
+            json
+        })
+        .collect::<Vec<_>>();
+    let mut json = serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title,
+        "home_page_url": feed.url,
+        "feed_url": format!("{}feed.json", feed.url),
+        "items": items,
+    });
+    if let (Some(json), Some(language)) = (json.as_object_mut(), &site.language) {
+        json.insert("language".to_owned(), language.as_str().into());
+    }
+    if let (Some(json), Some(description)) = (json.as_object_mut(), &site.description) {
+        json.insert("description".to_owned(), description.as_str().into());
+    }
+    serde_json::to_string_pretty(&json).unwrap_or_default()
+}
+
+/// Escape text for XML
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Make relative links (`href` and `src` attributes) in the HTML of a page absolute
+fn absolute_links(html: &str, base_url: &str, page_url: &str) -> String {
+    let page_directory = &page_url[..page_url.rfind('/').map_or(0, |index| index + 1)];
+    let mut absolute = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(index) = ["href=\"", "src=\""]
+        .iter()
+        .filter_map(|attribute| rest.find(attribute).map(|index| index + attribute.len()))
+        .min()
+    {
+        absolute.push_str(&rest[..index]);
+        rest = &rest[index..];
+        let link = &rest[..rest.find('"').unwrap_or(rest.len())];
+        if link.starts_with("//") || link.contains(':') {
+            // Already absolute, or a scheme like mailto:
+        } else if link.starts_with('/') {
+            absolute.push_str(base_url);
+        } else if link.starts_with('#') {
+            absolute.push_str(base_url);
+            absolute.push_str(page_url);
+        } else {
+            absolute.push_str(base_url);
+            absolute.push_str(page_directory);
+        }
+        absolute.push_str(link);
+        rest = &rest[link.len()..];
+    }
+    absolute.push_str(rest);
+    absolute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(path: &str, url: &str, front_matter: &str) -> site::PageEntry {
+        site::PageEntry {
+            path: PathBuf::from(path),
+            url: url.to_owned(),
+            metadata: serde_yml::from_str(front_matter).unwrap(),
+            info: Default::default(),
+        }
+    }
+
+    fn read_json(path: &Path) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            xml_escape(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn links() {
+        let html = concat!(
+            r##"<a href="/about/">a</a> <img src="cat.png"> <a href="#top">b</a> "##,
+            r#"<a href="https://rust-lang.org">c</a> <a href="//cdn.net/x">d</a> "#,
+            r#"<a href="mailto:me@example.com">e</a>"#,
+        );
+        assert_eq!(
+            absolute_links(html, "https://example.com", "/blog/post.html"),
+            concat!(
+                r#"<a href="https://example.com/about/">a</a> "#,
+                r#"<img src="https://example.com/blog/cat.png"> "#,
+                r##"<a href="https://example.com/blog/post.html#top">b</a> "##,
+                r#"<a href="https://rust-lang.org">c</a> <a href="//cdn.net/x">d</a> "#,
+                r#"<a href="mailto:me@example.com">e</a>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn directory_urls() {
+        assert_eq!(directory_url(Path::new("")), "/");
+        assert_eq!(directory_url(Path::new("blog")), "/blog/");
+        assert_eq!(
+            directory_url(&Path::new("tags").join("rust")),
+            "/tags/rust/"
+        );
+    }
+
+    #[test]
+    fn tags_grouped_by_slug() {
+        let (groups, skipped) = group_tags(["rust", "Rust", "Web Dev", "!!!", "rust"]);
+        assert_eq!(
+            groups,
+            BTreeMap::from([
+                ("rust".to_owned(), "Rust"),
+                ("web-dev".to_owned(), "Web Dev")
+            ])
+        );
+        assert_eq!(skipped, BTreeSet::from(["!!!"]));
+    }
+
+    #[test]
+    fn feed_paths_with_pretty_urls() {
+        let mut generator = OreStaty::new(
+            Config {
+                base_url: Some("https://example.com/".to_owned()),
+                pretty_urls: true,
+                feeds: Some(FeedConfig {
+                    sections: vec!["blog".to_owned()],
+                    tags: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Path::new(""),
+        );
+        generator.site.pages = vec![
+            page("blog/a.md", "/blog/a/", "date: 2024-01-05\ntags: [Rust]"),
+            page("b.md", "/b/", "date: 2024-01-06\ntags: [rust, '!!!']"),
+            page("c.md", "/c/", "title: Undated"),
+        ];
+        let dst = std::env::temp_dir().join(format!("orestaty-feeds-{}", std::process::id()));
+        generator.build_feeds(&[], &dst);
+
+        let feed = read_json(&dst.join("feed.json"));
+        assert_eq!(feed["home_page_url"], "https://example.com/");
+        assert_eq!(feed["items"][0]["url"], "https://example.com/b/");
+        assert_eq!(feed["items"].as_array().unwrap().len(), 2);
+        let blog = read_json(&dst.join("blog").join("feed.json"));
+        assert_eq!(blog["home_page_url"], "https://example.com/blog/");
+        assert_eq!(blog["items"].as_array().unwrap().len(), 1);
+        let rust = read_json(&dst.join("tags").join("rust").join("feed.json"));
+        assert_eq!(rust["home_page_url"], "https://example.com/tags/rust/");
+        assert_eq!(rust["items"].as_array().unwrap().len(), 2);
+        assert!(dst.join("tags").join("rust").join("feed.xml").exists());
+        assert!(dst.join("atom.xml").exists());
+        assert_eq!(generator.manifest().files.len(), 9);
+        assert_eq!(generator.errors(), 0);
+        assert_eq!(generator.diagnostics().len(), 1);
+
+        std::fs::remove_dir_all(&dst).unwrap();
+    }
+}
@@ -1,24 +1,84 @@
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
-/// Copy all files from src to dst recursively
-pub fn copy_recursively(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
+/// Copy all files from src to dst recursively. Returns paths of all copied files, and paths of
+/// files and directories that failed to copy, with their errors
+pub fn copy_recursively(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> (Vec<PathBuf>, Vec<(PathBuf, std::io::Error)>) {
     // Source: https://stackoverflow.com/questions/26958489/how-to-copy-a-folder-recursively-in-rust
-    std::fs::create_dir_all(&dst)?;
-    for entry in std::fs::read_dir(src)? {
-        let copy = || -> std::io::Result<()> {
-            let entry = entry?;
-            let ty = entry.file_type()?;
-            if ty.is_dir() {
-                copy_recursively(entry.path(), dst.as_ref().join(entry.file_name()))?;
-            } else {
-                std::fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let mut copied = Vec::new();
+    let mut failed = Vec::new();
+    let entries = match std::fs::create_dir_all(dst).and_then(|_| std::fs::read_dir(src)) {
+        Ok(entries) => entries,
+        Err(err) => {
+            failed.push((src.to_owned(), err));
+            return (copied, failed);
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                failed.push((src.to_owned(), err));
+                continue;
             }
-            Ok(())
-        };
-        if let Err(err) = copy() {
-            eprintln!("Failed to copy asset: {}", err);
-            continue;
         };
+        let dst = dst.join(entry.file_name());
+        match entry.file_type() {
+            Ok(ty) if ty.is_dir() => {
+                let (mut dir_copied, mut dir_failed) = copy_recursively(entry.path(), dst);
+                copied.append(&mut dir_copied);
+                failed.append(&mut dir_failed);
+            }
+            Ok(_) => match std::fs::copy(entry.path(), &dst) {
+                Ok(_) => copied.push(dst),
+                Err(err) => failed.push((entry.path(), err)),
+            },
+            Err(err) => failed.push((entry.path(), err)),
+        }
+    }
+    (copied, failed)
+}
+
+/// Remove a file and all directories, that became empty because of that, up to `root`
+pub fn remove_file(path: &Path, root: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)?;
+    for directory in path.ancestors().skip(1) {
+        if !directory.starts_with(root) || directory == root {
+            break;
+        }
+        if std::fs::remove_dir(directory).is_err() {
+            break;
+        }
     }
     Ok(())
 }
+
+/// List of files generated by a build
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Generated files
+    pub files: BTreeSet<PathBuf>,
+}
+
+impl Manifest {
+    /// Load manifest from a file. Missing or invalid manifest is treated as empty
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|manifest| serde_json::from_str(&manifest).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save manifest into a file
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+}
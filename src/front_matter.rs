@@ -0,0 +1,267 @@
+use crate::{diagnostic, BuildResult, Diagnostic};
+
+/// Front matter format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Format {
+    /// YAML, between `---` lines
+    Yaml,
+    /// TOML, between `+++` lines
+    Toml,
+    /// JSON object
+    Json,
+}
+
+/// Front matter block at the beginning of a source
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrontMatter<'a> {
+    /// Format of the front matter
+    pub format: Format,
+    /// Front matter text, without delimiters (JSON object includes it's braces)
+    pub text: &'a str,
+}
+
+impl FrontMatter<'_> {
+    /// Parse front matter. Errors are located relative to the start of the source
+    pub fn parse<T: serde::de::DeserializeOwned + 'static>(&self) -> BuildResult<T> {
+        // YAML and TOML front matter starts after the opening delimiter line
+        match self.format {
+            Format::Yaml => serde_yml::from_str(self.text)
+                .map_err(|err| Diagnostic::from_yaml(&err).offset_lines(1)),
+            Format::Toml => toml::from_str::<toml::Table>(self.text)
+                .and_then(|table| datetimes_to_strings(toml::Value::Table(table)).try_into())
+                .map_err(|err| {
+                    // Errors after converting dates have no location, so find it by parsing
+                    // the front matter directly
+                    let located = toml::from_str::<T>(self.text)
+                        .err()
+                        .filter(|located| err.message().starts_with(located.message()));
+                    Diagnostic::from_toml(located.as_ref().unwrap_or(&err), self.text)
+                        .offset_lines(1)
+                }),
+            Format::Json => {
+                serde_json::from_str(self.text).map_err(|err| Diagnostic::from_json(&err))
+            }
+        }
+        .map_err(|diagnostic| diagnostic.context(diagnostic::Code::Metadata, "Invalid metadata"))
+    }
+
+    /// Parse front matter, using the default value if there is no front matter
+    pub fn parse_or_default<T: serde::de::DeserializeOwned + Default + 'static>(
+        front_matter: Option<Self>,
+    ) -> BuildResult<T> {
+        front_matter.map_or_else(|| Ok(T::default()), |front_matter| front_matter.parse())
+    }
+}
+
+/// Replace TOML dates and times with strings (like `2024-01-05`), as [`markdown::Metadata`]
+/// expects dates to be strings, and other types can't represent them
+///
+/// [`markdown::Metadata`]: crate::markdown::Metadata
+fn datetimes_to_strings(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Datetime(datetime) => toml::Value::String(datetime.to_string()),
+        toml::Value::Array(array) => {
+            toml::Value::Array(array.into_iter().map(datetimes_to_strings).collect())
+        }
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, datetimes_to_strings(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Split front matter (YAML between `---` lines, TOML between `+++` lines or a JSON object at
+/// the very beginning of the file) from the rest of the source. Returns the front matter, if
+/// there is any, the rest of the source and the number of lines before it
+pub fn split(source: &str) -> (Option<FrontMatter<'_>>, &str, usize) {
+    if let Some(end) = json_object_end(source) {
+        let rest = &source[end..];
+        let rest = rest.find('\n').map_or("", |line_end| &rest[line_end + 1..]);
+        let front_matter = &source[..source.len() - rest.len()];
+        return (
+            Some(FrontMatter {
+                format: Format::Json,
+                text: &source[..end],
+            }),
+            rest,
+            front_matter.matches('\n').count(),
+        );
+    }
+
+    for (delimiter, format) in [("---", Format::Yaml), ("+++", Format::Toml)] {
+        let Some(text) = source.strip_prefix(delimiter).and_then(|text| {
+            text.strip_prefix('\n')
+                .or_else(|| text.strip_prefix("\r\n"))
+        }) else {
+            continue;
+        };
+
+        let mut offset = 0;
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            if line.trim_end() == delimiter {
+                return (
+                    Some(FrontMatter {
+                        format,
+                        text: &text[..offset],
+                    }),
+                    &text[offset + line.len()..],
+                    index + 2,
+                );
+            }
+            offset += line.len();
+        }
+    }
+    (None, source, 0)
+}
+
+/// Find the end of the JSON object at the beginning of the source. Handlebars expressions
+/// (`{{`) are not treated as JSON. Unclosed object spans the whole source
+fn json_object_end(source: &str) -> Option<usize> {
+    let rest = source.strip_prefix('{')?;
+    if !rest.starts_with(|ch: char| ch.is_whitespace() || ch == '"' || ch == '}') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, ch) in source.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    Some(source.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn front_matter(format: Format, text: &str) -> Option<FrontMatter<'_>> {
+        Some(FrontMatter { format, text })
+    }
+
+    #[test]
+    fn no_front_matter() {
+        assert_eq!(split("# Title\n"), (None, "# Title\n", 0));
+        assert_eq!(split("---"), (None, "---", 0));
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(
+            split("---\ntitle: A\n---\nBody\n"),
+            (front_matter(Format::Yaml, "title: A\n"), "Body\n", 3)
+        );
+    }
+
+    #[test]
+    fn toml() {
+        assert_eq!(
+            split("+++\ntitle = \"A\"\ndraft = true\n+++\nBody"),
+            (
+                front_matter(Format::Toml, "title = \"A\"\ndraft = true\n"),
+                "Body",
+                4
+            )
+        );
+    }
+
+    #[test]
+    fn crlf_delimiters() {
+        assert_eq!(
+            split("---\r\ntitle: A\r\n---\r\nBody\r\n"),
+            (front_matter(Format::Yaml, "title: A\r\n"), "Body\r\n", 3)
+        );
+        assert_eq!(
+            split("+++\r\ntitle = \"A\"\r\n+++\r\nBody\r\n"),
+            (
+                front_matter(Format::Toml, "title = \"A\"\r\n"),
+                "Body\r\n",
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn unclosed_delimiter() {
+        let source = "---\ntitle: A\n\nBody\n";
+        assert_eq!(split(source), (None, source, 0));
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            split("{\n  \"title\": \"A\"\n}\nBody\n"),
+            (
+                front_matter(Format::Json, "{\n  \"title\": \"A\"\n}"),
+                "Body\n",
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn json_braces_in_strings() {
+        let object = r#"{"title": "a } b", "quote": "\" }", "nested": {"a": 1}}"#;
+        assert_eq!(
+            json_object_end(&format!("{}\nBody", object)),
+            Some(object.len())
+        );
+    }
+
+    #[test]
+    fn json_crlf() {
+        assert_eq!(
+            split("{\"title\": \"A\"}\r\nBody\r\n"),
+            (
+                front_matter(Format::Json, "{\"title\": \"A\"}"),
+                "Body\r\n",
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn handlebars_is_not_json() {
+        assert_eq!(json_object_end("{{ metadata.title }}\n"), None);
+        assert_eq!(json_object_end("{{#if x}}{ \"a\": 1 }{{/if}}"), None);
+    }
+
+    #[test]
+    fn unclosed_json_spans_whole_source() {
+        // Reported as invalid metadata, instead of rendering half of the object as content
+        let source = "{\n  \"title\": \"A\"\n\n# Body\n";
+        let (front_matter, rest, lines) = split(source);
+        assert_eq!(front_matter, self::front_matter(Format::Json, source));
+        assert_eq!((rest, lines), ("", 4));
+        assert!(front_matter.unwrap().parse::<serde_json::Value>().is_err());
+    }
+
+    #[test]
+    fn toml_dates() {
+        let metadata = FrontMatter {
+            format: Format::Toml,
+            text: "date = 2024-01-05\n[extra]\nat = 1979-05-27T07:32:00Z\n",
+        }
+        .parse::<serde_json::Value>()
+        .unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!({ "date": "2024-01-05", "extra": { "at": "1979-05-27T07:32:00Z" } })
+        );
+    }
+}
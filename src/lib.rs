@@ -1,11 +1,12 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 #![allow(clippy::doc_lazy_continuation)]
-#![allow(clippy::result_unit_err)]
 
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+pub use diagnostic::{BuildResult, Diagnostic};
+
 pub use handlebars;
 pub use serde;
 pub use serde_json;
@@ -13,34 +14,69 @@ pub use serde_json;
 pub use grass;
 pub use pulldown_cmark;
 
+/// Incremental build cache
+pub mod cache;
+/// Global data from data files
+pub mod data;
+/// Build errors and warnings
+pub mod diagnostic;
+/// RSS, Atom and JSON feeds
+pub mod feeds;
 /// File utilities
 pub mod files;
+/// Front matter of sources and templates
+pub mod front_matter;
 /// Build HTML page with Markdown
 pub mod markdown;
 /// Build HTML page with Handlebars
 pub mod page;
+/// Source file processors
+pub mod processors;
 /// Build CSS with SASS
 pub mod sass;
+/// Development server with live reload
+pub mod serve;
+/// Site-wide page index
+pub mod site;
+/// Text utilities
+pub mod text;
+/// Watch project files for changes
+pub mod watch;
 
 /// Built-in plugins
 pub mod plugins;
 
-type ArcMutex<T> = std::sync::Arc<std::sync::Mutex<T>>;
-
 /// Generator struct, see [`Self::build`]
 #[derive(Debug)]
 pub struct OreStaty<'a> {
     /// Handlebars renderer
     pub handlebars: handlebars::Handlebars<'a>,
     /// Syntax highlighting context
-    pub syntax_highlighting: ArcMutex<plugins::syntax_highlighting::SyntaxHighlighting>,
+    pub syntax_highlighting: std::sync::Arc<plugins::syntax_highlighting::SyntaxHighlighting>,
     /// SASS rendering options
-    pub sass_options: grass::Options<'a>,
+    pub sass_options: sass::SassOptions,
     /// Markdown (Commonmark) rendering options
     pub markdown_options: pulldown_cmark::Options,
+    /// Build cache. If set, only outputs with changed dependencies are rebuilt
+    pub cache: Option<cache::BuildCache>,
+    /// Number of threads to build with, 0 to use all CPU cores
+    pub jobs: usize,
+    /// Build pages marked as drafts in front matter
+    pub drafts: bool,
+    /// Build pages with the publish date in the future
+    pub future: bool,
+    /// Source file processors and extensions they handle
+    pub processors: processors::Processors,
     /// Config
     config: Config,
-    errors: u32,
+    site: site::Site,
+    data: serde_json::Map<String, serde_json::Value>,
+    plugins: Vec<std::path::PathBuf>,
+    templates: std::collections::HashMap<String, page::TemplateInfo>,
+    manifest: files::Manifest,
+    ignore: globset::GlobSet,
+    /// Shared with helpers, that report warnings while rendering
+    diagnostics: std::sync::Arc<std::sync::Mutex<Vec<Diagnostic>>>,
 }
 
 fn default_template() -> String {
@@ -56,8 +92,21 @@ fn default_highlight_theme() -> String {
 }
 
 /// Generator config
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Site title
+    pub title: Option<String>,
+    /// URL the site is hosted at, like `https://example.com`
+    pub base_url: Option<String>,
+    /// Site language, like `en`
+    pub language: Option<String>,
+    /// Site description
+    pub description: Option<String>,
+    /// Custom parameters, available in templates as `site.params`
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+
     /// Default tempate
     #[serde(default = "default_template")]
     pub default_template: String,
@@ -68,14 +117,54 @@ pub struct Config {
     /// Default highlight theme
     #[serde(default = "default_highlight_theme")]
     pub code_highlight_theme: String,
+    /// Add self-link anchors (`<a class="anchor" href="#id">#</a>`) to markdown headings
+    #[serde(default)]
+    pub heading_anchors: bool,
+
+    /// Glob patterns of files in the output directory, that must never be removed when
+    /// cleaning stale outputs
+    #[serde(default)]
+    pub clean_exclude: Vec<String>,
+    /// Glob patterns of files and directories in the source directory, that must not be built.
+    /// Files and directories starting with `_` are always ignored
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Permalink patterns for sections (directories in "src"), like `blog = "/blog/:year/:slug/"`.
+    /// See [`OreStaty::permalink`] for the supported placeholders
+    #[serde(default)]
+    pub permalinks: std::collections::BTreeMap<String, String>,
+    /// Build HTML pages into their own directories (`about/index.html` instead of `about.html`)
+    /// to get URLs without extension
+    #[serde(default)]
+    pub pretty_urls: bool,
+    /// Additional source extensions, mapped to a processor (`page`, `markdown`, `sass` or one
+    /// registered by a library user). Mapped at the start of every [`OreStaty::build`], so
+    /// processors can be registered after creating the generator
+    #[serde(default)]
+    pub extensions: std::collections::BTreeMap<String, processors::ExtensionMapping>,
+    /// Generate feeds, see [`feeds::FeedConfig`]
+    #[serde(default)]
+    pub feeds: Option<feeds::FeedConfig>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            title: None,
+            base_url: None,
+            language: None,
+            description: None,
+            params: Default::default(),
             default_template: default_template(),
             default_markdown_template: default_markdown_template(),
             code_highlight_theme: default_highlight_theme(),
+            heading_anchors: false,
+            clean_exclude: Vec::new(),
+            ignore: Vec::new(),
+            pretty_urls: false,
+            permalinks: Default::default(),
+            extensions: Default::default(),
+            feeds: None,
         }
     }
 }
@@ -93,21 +182,33 @@ impl OreStaty<'_> {
         handlebars
             .register_template_string("default", "{{{content}}}")
             .expect("Failed to register default template! Buggy build");
-        Self {
+        let mut generator = Self {
             handlebars,
-            syntax_highlighting: ArcMutex::new(
+            syntax_highlighting: std::sync::Arc::new(
                 plugins::syntax_highlighting::SyntaxHighlighting::new(
                     &config.code_highlight_theme,
                     root_path,
                 )
-                .unwrap_or_default()
-                .into(),
+                .unwrap_or_default(),
             ),
-            sass_options: grass::Options::default(),
+            sass_options: Default::default(),
             markdown_options: pulldown_cmark::Options::all(),
+            cache: None,
+            jobs: 0,
+            drafts: false,
+            future: false,
+            processors: Default::default(),
+            site: site::Site::new(&config),
             config,
-            errors: 0,
-        }
+            data: Default::default(),
+            plugins: Vec::new(),
+            templates: Default::default(),
+            manifest: files::Manifest::default(),
+            ignore: globset::GlobSet::empty(),
+            diagnostics: Default::default(),
+        };
+        generator.ignore = generator.glob_set(&generator.config.ignore);
+        generator
     }
 
     /// Get config of the generator
@@ -115,122 +216,328 @@ impl OreStaty<'_> {
         &self.config
     }
 
-    /// Report an error
-    pub fn error(&mut self, message: &str) -> &mut Self {
-        eprintln!("{}", message);
-        self.errors += 1;
+    /// Compile glob patterns, reporting invalid ones
+    pub fn glob_set(&self, patterns: &[String]) -> globset::GlobSet {
+        let invalid_pattern =
+            |err: globset::Error| Diagnostic::error(diagnostic::Code::Config, err.to_string());
+        let mut globs = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            if let Some(glob) =
+                self.unwrap_or_report(globset::Glob::new(pattern).map_err(invalid_pattern))
+            {
+                globs.add(glob);
+            }
+        }
+        self.unwrap_or_report(globs.build().map_err(invalid_pattern))
+            .unwrap_or_else(globset::GlobSet::empty)
+    }
+
+    /// Check if a file or directory in the source directory should not be built: it's name
+    /// starts with `_` (like SASS partials), or it matches [`Config::ignore`]
+    pub fn is_ignored(&self, relative_path: &Path) -> bool {
+        relative_path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('_'))
+            || self.ignore.is_match(relative_path)
+    }
+
+    /// Report an error or a warning. Can be called from multiple threads
+    pub fn report(&self, diagnostic: Diagnostic) -> &Self {
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(diagnostic);
         self
     }
 
     /// "Unwrap" a result, report if error
-    pub fn unwrap_or_error<T, E: std::fmt::Display>(
-        &mut self,
-        result: Result<T, E>,
-        message: impl AsRef<str>,
-    ) -> Result<T, ()> {
+    pub fn unwrap_or_report<T>(&self, result: BuildResult<T>) -> Option<T> {
         match result {
-            Ok(result) => Ok(result),
-            Err(err) => {
-                self.error(&format!("{}: {}", message.as_ref(), err));
-                Err(())
+            Ok(result) => Some(result),
+            Err(diagnostic) => {
+                self.report(diagnostic);
+                None
             }
         }
     }
 
-    /// Get the number of errors reported during build
+    /// Get the number of errors reported
     pub fn errors(&self) -> u32 {
-        self.errors
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .count() as u32
     }
-}
 
-impl OreStaty<'_> {
-    /// Build all sources in the given path, outputting to the given destination
-    pub fn build(&mut self, src: &std::path::Path, dst: &std::path::Path) {
-        self.build_dir(src, dst, Path::new("")).unwrap();
+    /// Get all errors and warnings reported
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
     }
 
-    /// Build all sources in the given directory, with it's relative path specified
-    pub fn build_dir(&mut self, src: &Path, dst: &Path, relative_path: &Path) -> Result<(), ()> {
-        for file in self.unwrap_or_error(
-            std::fs::read_dir(src),
-            format!("Failed to read directory {:?}", src),
-        )? {
-            let Ok(file) = self.unwrap_or_error(file, "Failed to read file") else {
-                continue;
-            };
-            let name = std::path::PathBuf::from(file.file_name());
-            let dst = dst.join(&name);
-            let relative_path = relative_path.join(&name);
+    /// Forget all reported errors and warnings, for example before rebuilding
+    pub fn clear_diagnostics(&mut self) {
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear();
+    }
+}
 
-            if file.path().is_file() {
-                let Ok((built, extension)) = self.build_file(&file.path(), &relative_path) else {
-                    continue;
-                };
-                self.write_file(&built, &dst.with_extension(extension)).ok();
-            } else {
-                self.build_dir(&file.path(), &dst, &relative_path)?;
+impl OreStaty<'_> {
+    /// Build all sources in the given path, outputting to the given destination.
+    /// Uses [`Self::jobs`] threads. Extensions from [`Config::extensions`] are mapped first.
+    /// Returns errors and warnings reported during the build
+    pub fn build(&mut self, src: &std::path::Path, dst: &std::path::Path) -> Vec<Diagnostic> {
+        let reported = self.diagnostics().len();
+        self.map_config_extensions();
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+        {
+            Ok(pool) => {
+                self.manifest = files::Manifest::default();
+                pool.install(|| self.build_dir(src, dst, Path::new("")));
+            }
+            Err(err) => {
+                self.report(Diagnostic::error(
+                    diagnostic::Code::Io,
+                    format!("Failed to create thread pool: {}", err),
+                ));
             }
         }
-        Ok(())
+
+        let mut diagnostics = self
+            .diagnostics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        // Sources are built in parallel, so sort to get a stable order
+        diagnostics[reported..].sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        diagnostics[reported..].to_vec()
     }
 
-    /// Build a single source file
-    pub fn build_file(
-        &mut self,
-        src: &Path,
-        relative_path: &Path,
-    ) -> Result<(String, &'static str), ()> {
-        match src
-            .extension()
-            .map_or(std::borrow::Cow::Borrowed(""), |ext| ext.to_string_lossy())
-            .as_ref()
-        {
-            "html" | "htm" | "hbs" => self
-                .build_page(src, relative_path)
-                .map(|built| (built, "html")),
-            "sass" | "scss" | "css" => self
-                .build_sass(src, relative_path)
-                .map(|built| (built, "css")),
-            "md" | "markdown" => self
-                .build_markdown(src, relative_path)
-                .map(|built| (built, "html")),
-            ext => {
-                eprintln!("Warning: {:?} file extension is unknown. Skipping {:?}; Maybe you wanted to put it into \"static\" directory?", ext, src);
-                Err(())
+    /// Build all sources in the given directory, with it's relative path specified.
+    /// All sources get indexed first, so that every page can access `site.pages`. Pages, that
+    /// are not published yet (see [`Self::is_published`]) are skipped, as well as sources built
+    /// into the same output file as another source (reported as errors).
+    /// Sources are indexed and built in parallel
+    pub fn build_dir(&mut self, src: &Path, dst: &Path, relative_path: &Path) {
+        use rayon::prelude::*;
+
+        let mut sources = self.collect_sources(src, relative_path);
+        let pages = sources
+            .par_iter_mut()
+            .map(|source| self.index_source(source))
+            .collect::<Vec<_>>();
+        let (sources, pages): (Vec<_>, Vec<_>) = sources
+            .into_iter()
+            .zip(pages)
+            .filter(|(_, page)| {
+                page.as_ref()
+                    .is_none_or(|page| self.is_published(&page.metadata))
+            })
+            .unzip();
+
+        // Sources built into the same file (like `about.md` and `about/index.md` with pretty
+        // URLs) would overwrite each other, so only the first one by path is built
+        let mut first = std::collections::HashMap::<&Path, &Path>::new();
+        for source in &sources {
+            let path = first.entry(&source.output).or_insert(&source.relative_path);
+            *path = std::cmp::min(*path, &source.relative_path);
+        }
+        let first = first
+            .into_iter()
+            .map(|(output, path)| (output.to_owned(), path.to_owned()))
+            .collect::<std::collections::HashMap<_, _>>();
+        let (sources, pages): (Vec<_>, Vec<_>) = sources
+            .into_iter()
+            .zip(pages)
+            .filter(|(source, _)| {
+                let path = &first[&source.output];
+                if *path != source.relative_path {
+                    self.report(
+                        Diagnostic::error(
+                            diagnostic::Code::Metadata,
+                            format!(
+                                "Output {:?} is already built from {:?}, skipping",
+                                source.output, path
+                            ),
+                        )
+                        .in_file(&source.path),
+                    );
+                }
+                *path == source.relative_path
+            })
+            .unzip();
+        self.site.pages = pages.into_iter().flatten().collect();
+
+        self.manifest
+            .files
+            .extend(sources.iter().map(|source| dst.join(&source.output)));
+
+        let fingerprints = self.fingerprints();
+        let reported = self.diagnostics().len();
+        let built = sources
+            .par_iter()
+            .filter_map(|source| {
+                let output = dst.join(&source.output);
+                if let Some(cache) = &self.cache {
+                    if cache.is_fresh(&output, fingerprints) {
+                        return None;
+                    }
+                }
+
+                let dependencies = self.build_source(source).and_then(|(built, dependencies)| {
+                    self.write_file(&built, &output)?;
+                    Ok(dependencies)
+                });
+                Some((source, output, self.unwrap_or_report(dependencies)))
+            })
+            .collect::<Vec<_>>();
+
+        // Sources can report errors and still be built (like markdown with invalid front
+        // matter). They are not cached, so the errors are reported again on the next build
+        let failed = self.diagnostics()[reported..]
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .filter_map(|diagnostic| diagnostic.file.clone())
+            .collect::<std::collections::HashSet<_>>();
+        if let Some(cache) = &mut self.cache {
+            for (source, output, dependencies) in built {
+                match dependencies {
+                    Some(dependencies) if !failed.contains(&source.path) => {
+                        cache.insert(output, dependencies, fingerprints)
+                    }
+                    _ => cache.remove(&output),
+                }
             }
         }
+        self.build_feeds(&sources, dst);
     }
 
-    /// Write a file, reporting if error
-    pub fn write_file(&mut self, content: &str, dst: &Path) -> Result<(), ()> {
-        if let Ok(out_dir) = self.unwrap_or_error(
-            dst.parent().ok_or("No parent path"),
-            format!("Failed to create output directory for file {:?}", dst),
-        ) {
-            self.unwrap_or_error(
-                std::fs::create_dir_all(out_dir),
-                format!("Failed to create output directory for file {:?}", dst,),
+    /// Build a source with it's processor, also returning files the output depends on
+    pub fn build_source(
+        &self,
+        source: &site::Source,
+    ) -> BuildResult<(String, cache::Dependencies)> {
+        let processor = self.processors.get(&source.processor).ok_or_else(|| {
+            Diagnostic::error(
+                diagnostic::Code::UnknownSource,
+                format!("Unknown processor {:?}", source.processor),
             )
-            .ok();
+            .in_file(&source.path)
+        })?;
+        processor.build(self, source)
+    }
+
+    /// Build a single source file, returning the output and it's extension
+    pub fn build_file(&self, src: &Path, relative_path: &Path) -> BuildResult<(String, String)> {
+        let (processor, extension) = self.processors.find(src).ok_or_else(|| {
+            Diagnostic::error(
+                diagnostic::Code::UnknownSource,
+                format!(
+                    "Unknown file extension {:?}",
+                    src.extension().unwrap_or_default()
+                ),
+            )
+            .in_file(src)
+        })?;
+        let output = self.output_path(relative_path, &extension);
+        let source = site::Source {
+            path: src.to_owned(),
+            relative_path: relative_path.to_owned(),
+            url: self.output_url(&output),
+            output,
+            processor: processor.to_owned(),
+        };
+        self.build_source(&source)
+            .map(|(built, _)| (built, extension))
+    }
+
+    /// Write a file, creating the output directory if needed
+    pub fn write_file(&self, content: &str, dst: &Path) -> BuildResult<()> {
+        if let Some(out_dir) = dst.parent() {
+            std::fs::create_dir_all(out_dir).map_err(|err| {
+                Diagnostic::io(
+                    format!("Failed to create output directory for file {:?}", dst),
+                    err,
+                )
+            })?;
         }
 
-        self.unwrap_or_error(
-            std::fs::write(dst, content),
-            format!("Failed to write built HTML page for file {:?}", dst),
-        )
+        std::fs::write(dst, content)
+            .map_err(|err| Diagnostic::io("Failed to write built file", err).in_file(dst))
+    }
+}
+
+impl OreStaty<'_> {
+    /// Copy static files into the output directory, reporting files that failed to copy
+    pub fn copy_static(&mut self, src: &Path, dst: &Path) {
+        let (copied, failed) = files::copy_recursively(src, dst);
+        self.manifest.files.extend(copied);
+        for (path, err) in failed {
+            self.report(Diagnostic::io("Failed to copy static file", err).in_file(&path));
+        }
+    }
+
+    /// Get all files generated by the last build
+    pub fn manifest(&self) -> &files::Manifest {
+        &self.manifest
+    }
+
+    /// Remove files listed in the manifest of the previous build, that were not generated by
+    /// the last build. Only files in `dst` are removed, files of builds into other directories
+    /// are kept in the manifest. Files matching [`Config::clean_exclude`] are never removed
+    pub fn remove_stale(&mut self, previous: &files::Manifest, dst: &Path) {
+        let (stale, other): (Vec<_>, Vec<_>) = previous
+            .files
+            .iter()
+            .filter(|file| !self.manifest.files.contains(*file))
+            .partition(|file| file.starts_with(dst));
+        self.clean(stale, dst);
+        self.manifest.files.extend(other.into_iter().cloned());
+    }
+
+    /// Remove all given files, except those matching [`Config::clean_exclude`]. Files outside
+    /// of `dst` are never removed
+    pub fn clean<'p>(&self, files: impl IntoIterator<Item = &'p std::path::PathBuf>, dst: &Path) {
+        let exclude = self.glob_set(&self.config.clean_exclude);
+        for file in files {
+            let Ok(relative_path) = file.strip_prefix(dst) else {
+                continue;
+            };
+            if exclude.is_match(relative_path) || !file.exists() {
+                continue;
+            }
+            if let Err(err) = files::remove_file(file, dst) {
+                self.report(Diagnostic::io("Failed to remove stale file", err).in_file(file));
+            }
+        }
     }
 }
 
 impl OreStaty<'_> {
     /// Load plugin helpers and templates from specified path. Set scope to an empty string if
     /// loading from root
-    pub fn load_plugins(&mut self, path: &Path, scope: &str) -> Result<(), ()> {
-        for file in self.unwrap_or_error(
-            std::fs::read_dir(path),
-            format!("Failed to read plugin directory {:?}", path),
-        )? {
-            let Ok(file) = self.unwrap_or_error(file, "Failed to read file") else {
-                continue;
+    pub fn load_plugins(&mut self, path: &Path, scope: &str) {
+        let files = match std::fs::read_dir(path) {
+            Ok(files) => files,
+            Err(err) => {
+                self.report(Diagnostic::io("Failed to read plugin directory", err).in_file(path));
+                return;
+            }
+        };
+        for file in files {
+            let file = match file {
+                Ok(file) => file,
+                Err(err) => {
+                    self.report(Diagnostic::io("Failed to read file", err).in_file(path));
+                    continue;
+                }
             };
 
             let name = file.file_name();
@@ -252,22 +559,25 @@ impl OreStaty<'_> {
                     .as_str()
                 {
                     "html" | "htm" | "hbs" => {
-                        let result = self.handlebars.register_template_file(&name, &file);
-                        self.unwrap_or_error(
-                            result,
-                            format!("Failed to register {:?} as Handlebars template", file),
-                        )
-                        .unwrap_or(())
+                        self.plugins.push(file.clone());
+                        if let Err(diagnostic) = self.register_template_file(&name, &file) {
+                            self.report(diagnostic.or_in_file(&file));
+                        }
                     }
                     "rhai" => {
-                        let result = self
+                        self.plugins.push(file.clone());
+                        if let Err(err) = self
                             .handlebars
-                            .register_script_helper_file(&name.replace('.', "_"), &file);
-                        self.unwrap_or_error(
-                            result,
-                            format!("Failed to register {:?} as Handlebars template", file),
-                        )
-                        .unwrap_or(())
+                            .register_script_helper_file(&name.replace('.', "_"), &file)
+                        {
+                            self.report(
+                                Diagnostic::error(
+                                    diagnostic::Code::Plugin,
+                                    format!("Failed to register as Handlebars helper: {}", err),
+                                )
+                                .in_file(&file),
+                            );
+                        }
                     }
                     // ext => eprintln!(
                     //     "Warning: {:?} file extension is unknown. Skipping {:?}",
@@ -276,9 +586,15 @@ impl OreStaty<'_> {
                     _ => (),
                 }
             } else {
-                self.load_plugins(&file, &name).ok();
+                self.load_plugins(&file, &name);
             }
         }
-        Ok(())
+    }
+
+    /// Get the file a plugin template was loaded from
+    pub fn template_file(&self, name: &str) -> Option<&Path> {
+        self.templates
+            .get(name)
+            .map(|template| template.path.as_path())
     }
 }
@@ -12,6 +12,22 @@ struct Cli {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Rebuild everything, ignoring the build cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Number of threads to build with, all CPU cores by default
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Build pages marked as drafts
+    #[arg(long, global = true)]
+    drafts: bool,
+
+    /// Build pages with the publish date in the future
+    #[arg(long, global = true)]
+    future: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -23,6 +39,16 @@ enum Commands {
     /// Build the website
     #[default]
     Build,
+    /// Build the website and rebuild it every time something changes
+    Watch,
+    /// Remove all generated files and the build cache
+    Clean,
+    /// Serve the website on localhost, rebuilding and reloading it every time something changes
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
     /// Generate .css file from a colorscheme
     ThemeToCSS {
         /// Theme name or path to .tmTheme
@@ -33,23 +59,29 @@ enum Commands {
     },
 }
 
-fn parse_config(path: &std::path::Path) -> Result<orestaty::Config, ()> {
-    if path.exists() {
-        let config = std::fs::read_to_string(path).map_err(|err| {
-            eprintln!("Failed to read config file: {}", err);
-        })?;
-        toml::from_str(&config).map_err(|err| {
-            eprintln!("Failed to parse config file: {}", err);
-        })
-    } else {
-        Err(())
-    }
+fn parse_config(path: &std::path::Path) -> Result<orestaty::Config, orestaty::Diagnostic> {
+    let config = std::fs::read_to_string(path)
+        .map_err(|err| orestaty::Diagnostic::io("Failed to read config file", err).in_file(path))?;
+    toml::from_str(&config)
+        .map_err(|err| orestaty::Diagnostic::from_toml(&err, &config).in_file(path))
+}
+
+/// Read ignore patterns from a file, one per line. Empty lines and lines starting with `#` are
+/// skipped
+fn parse_ignore_file(path: &std::path::Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
 }
 
 fn main() {
     let cli = Cli::parse();
-    let path = cli.path.unwrap_or(std::env::current_dir().unwrap());
-    let dst = cli.output.unwrap_or(path.join("dist"));
+    let path = cli.path.clone().unwrap_or(std::env::current_dir().unwrap());
+    let dst = cli.output.clone().unwrap_or(path.join("dist"));
 
     if cli.command == Commands::Init {
         if let Err(err) = std::fs::create_dir_all(path.join("src")) {
@@ -83,32 +115,177 @@ fn main() {
         return;
     }
 
-    let config = parse_config(&path.join("config.toml")).unwrap_or_default();
-    let mut generator = orestaty::OreStaty::new(config, &path);
+    let mut generator = create_generator(&cli, &path);
+    match cli.command {
+        Commands::Clean => {
+            let manifest_path = path.join(".orestaty-cache").join("manifest.json");
+            generator.clean(&orestaty::files::Manifest::load(&manifest_path).files, &dst);
+            if path.join(".orestaty-cache").exists() {
+                if let Err(err) = std::fs::remove_dir_all(path.join(".orestaty-cache")) {
+                    generator.report(orestaty::Diagnostic::io(
+                        "Failed to remove build cache",
+                        err,
+                    ));
+                }
+            }
+            print_diagnostics(&generator);
+        }
+        Commands::Build => {
+            build(&mut generator, &path, &dst);
+            if generator.errors() > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Watch => {
+            build(&mut generator, &path, &dst);
+            watch(&cli, &path, &dst, generator, |_| ());
+        }
+        Commands::Serve { port } => {
+            build(&mut generator, &path, &dst);
+            let server = match orestaty::serve::Server::new(("127.0.0.1", port), &dst) {
+                Ok(server) => server,
+                Err(err) => {
+                    eprintln!("Failed to start server: {}", err);
+                    std::process::exit(1);
+                }
+            };
+            server.set_errors(generator.diagnostics());
+            server.spawn(|diagnostic| eprintln!("{}", diagnostic));
+            println!("Serving on http://{}", server.address());
+            watch(&cli, &path, &dst, generator, |generator| {
+                server.set_errors(generator.diagnostics());
+                server.reload();
+            });
+        }
+        _ => unreachable!(),
+    }
+}
 
+/// Rebuild the project every time something changes, calling `on_rebuild` after each rebuild
+fn watch(
+    cli: &Cli,
+    path: &std::path::Path,
+    dst: &std::path::Path,
+    mut generator: orestaty::OreStaty,
+    on_rebuild: impl Fn(&orestaty::OreStaty),
+) {
+    let watcher = match orestaty::watch::Watcher::new(path) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            eprintln!("Failed to watch project files: {}", err);
+            std::process::exit(1);
+        }
+    };
+    println!("Watching for changes...");
+    while let Some(changes) = watcher.wait() {
+        for diagnostic in &changes.errors {
+            eprintln!("{}", diagnostic);
+        }
+        if changes.paths.is_empty() {
+            continue;
+        }
+        println!("Rebuilding...");
+        if changes.plugins || changes.config {
+            generator = create_generator(cli, path);
+        } else {
+            generator.clear_diagnostics();
+            if changes.data {
+                load_data(&mut generator, path);
+            }
+        }
+        build(&mut generator, path, dst);
+        on_rebuild(&generator);
+    }
+}
+
+/// Create generator, loading config and plugins of the project
+fn create_generator(cli: &Cli, path: &std::path::Path) -> orestaty::OreStaty<'static> {
+    let config_path = path.join("config.toml");
+    let config = if config_path.exists() {
+        parse_config(&config_path)
+    } else {
+        Ok(orestaty::Config::default())
+    };
+    let mut generator = orestaty::OreStaty::new(
+        orestaty::Config {
+            ignore: [
+                config
+                    .as_ref()
+                    .map_or(Vec::new(), |config| config.ignore.clone()),
+                parse_ignore_file(&path.join(".orestatyignore")),
+            ]
+            .concat(),
+            ..config.clone().unwrap_or_default()
+        },
+        path,
+    );
+    if let Err(diagnostic) = config {
+        generator.report(diagnostic);
+    }
+
+    generator.jobs = cli.jobs.unwrap_or_default();
+    generator.drafts = cli.drafts;
+    generator.future = cli.future;
     generator.handlebars.set_strict_mode(true);
     generator.register_default_markdown_template();
     generator.register_builtin_plugins();
 
     let plugin_path = path.join("plugins");
     if plugin_path.exists() {
-        generator.sass_options = generator.sass_options.load_path(&plugin_path);
-        generator.load_plugins(&plugin_path, "").ok();
+        generator.sass_options.load_paths.push(plugin_path.clone());
+        generator.load_plugins(&plugin_path, "");
     }
 
-    match cli.command {
-        Commands::Build => {
-            generator.build(&path.join("src"), &dst);
-            if path.join("static").exists() {
-                generator
-                    .unwrap_or_error(
-                        orestaty::files::copy_recursively(path.join("static"), &dst),
-                        "Failed to copy static files",
-                    )
-                    .ok();
-            }
+    load_data(&mut generator, path);
+
+    let cache_path = path.join(".orestaty-cache");
+    generator.cache = Some(if cli.no_cache {
+        orestaty::cache::BuildCache {
+            path: cache_path,
+            ..Default::default()
         }
-        _ => unreachable!(),
+    } else {
+        orestaty::cache::BuildCache::load(&cache_path)
+    });
+    generator
+}
+
+/// Load data files of the project, if there are any
+fn load_data(generator: &mut orestaty::OreStaty, path: &std::path::Path) {
+    let data_path = path.join("data");
+    if data_path.exists() {
+        generator.load_data(&data_path);
+    }
+}
+
+/// Build the project, copy static files and report errors
+fn build(generator: &mut orestaty::OreStaty, path: &std::path::Path, dst: &std::path::Path) {
+    generator.build(&path.join("src"), dst);
+    if path.join("static").exists() {
+        generator.copy_static(&path.join("static"), dst);
+    }
+
+    let manifest_path = path.join(".orestaty-cache").join("manifest.json");
+    generator.remove_stale(&orestaty::files::Manifest::load(&manifest_path), dst);
+    if let Err(err) = generator.manifest().save(&manifest_path) {
+        generator.report(orestaty::Diagnostic::io(
+            "Failed to save output manifest",
+            err,
+        ));
+    }
+
+    if let Some(cache) = &generator.cache {
+        if let Err(err) = cache.save() {
+            generator.report(orestaty::Diagnostic::io("Failed to save build cache", err));
+        }
+    }
+    print_diagnostics(generator);
+}
+
+/// Print all errors and warnings
+fn print_diagnostics(generator: &orestaty::OreStaty) {
+    for diagnostic in generator.diagnostics() {
+        eprintln!("{}", diagnostic);
     }
 
     // * Check for errors and finish
@@ -122,6 +299,5 @@ fn main() {
                 "errors"
             }
         );
-        std::process::exit(1);
     }
 }
@@ -1,71 +1,527 @@
 use super::*;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
 
 /// Markdown page metadata
-#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Metadata {
     /// Page title
     pub title: Option<String>,
     /// Page template
     pub template: Option<String>,
+    /// Publication date, `YYYY-MM-DD` optionally followed by time
+    #[serde(default, deserialize_with = "date")]
+    pub date: Option<String>,
+    /// Date the page gets published at, if it's not `date`. Pages from the future are only
+    /// built with [`OreStaty::future`]
+    #[serde(default, deserialize_with = "date")]
+    pub publish_date: Option<String>,
+    /// Drafts are only built with [`OreStaty::drafts`]
+    #[serde(default)]
+    pub draft: bool,
+    /// All other fields, like `tags` or `description`
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// URL of the page, overriding the one derived from the source path, see
+    /// [`OreStaty::permalink`]
+    pub permalink: Option<String>,
+    /// Last part of the page URL, instead of the source file name
+    pub slug: Option<String>,
+    /// Render page content as a Handlebars template, `true` if not set
+    pub templating: Option<bool>,
+}
+
+/// Entry of the page table of contents, sent to template as an element of `page.toc`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TocEntry {
+    /// Heading level, 1 to 6
+    pub level: usize,
+    /// Heading id, to link to it
+    pub id: String,
+    /// Plain text of the heading
+    pub title: String,
+    /// Headings nested in this one
+    pub children: Vec<TocEntry>,
+}
+
+/// Text of a Handlebars tag in plain text (like summaries and heading ids): escaped tags are
+/// kept without the backslash, others are left out
+fn tag_text(tag: &str) -> String {
+    tag.strip_prefix('\\').unwrap_or_default().to_owned()
+}
+
+/// Deserialize a date from a string or a TOML date. TOML dates are usually converted to strings
+/// before deserializing, but not when locating errors (see [`front_matter::FrontMatter::parse`])
+fn date<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        String(String),
+        Toml(toml::value::Datetime),
+    }
+
+    Ok(
+        Option::<Date>::deserialize(deserializer)?.map(|date| match date {
+            Date::String(date) => date,
+            Date::Toml(date) => date.to_string(),
+        }),
+    )
+}
+
+/// Reading speed, used to estimate [`ContentInfo::reading_time`]
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Summary and size of page content, sent to template as part of `page` and `site.pages`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ContentInfo {
+    /// Plain text summary: content before `<!-- more -->` marker, or the first paragraph
+    pub summary: Option<String>,
+    /// Summary as HTML
+    pub summary_html: Option<String>,
+    /// Number of words in the content
+    pub word_count: usize,
+    /// Estimated reading time in minutes
+    pub reading_time: usize,
 }
 
 impl OreStaty<'_> {
-    /// Build a markdown page from the file using pulldown-cmark
-    pub fn build_markdown(&mut self, src: &Path, relative_path: &Path) -> Result<String, ()> {
+    /// Build a markdown page from the source using pulldown-cmark
+    pub fn build_markdown(&self, page: &site::Source) -> BuildResult<String> {
+        self.render_markdown(page, true)
+    }
+
+    /// Render the content of a markdown page, without it's template, see
+    /// [`Self::build_markdown`]
+    pub fn markdown_content(&self, page: &site::Source) -> BuildResult<String> {
+        self.render_markdown(page, false)
+    }
+
+    fn render_markdown(&self, page: &site::Source, with_template: bool) -> BuildResult<String> {
+        let (src, relative_path) = (&page.path, &page.relative_path);
         // * Read
-        let source = self.unwrap_or_error(std::fs::read_to_string(src), "Failed to read file")?;
-        let events = pulldown_cmark::Parser::new_ext(&source, self.markdown_options);
+        let source = std::fs::read_to_string(src)
+            .map_err(|err| Diagnostic::io("Failed to read file", err).in_file(src))?;
+        let (metadata, source, _) = front_matter::split(&source);
+        let metadata = front_matter::FrontMatter::parse_or_default::<Metadata>(metadata)
+            .unwrap_or_else(|diagnostic| {
+                self.report(diagnostic.in_file(src));
+                Metadata::default()
+            });
 
         // * Build
+        let templating = metadata.templating.unwrap_or(true);
+        let info = self.content_info(source, templating);
+        // Handlebars tags are replaced with placeholders, so that markdown doesn't escape them,
+        // code is rendered separately and restored after rendering the template, so that tags
+        // in it are not executed
+        let mut tags = page::Placeholders::new("orestatyhbs");
+        let source = if templating {
+            replace_tags(source, |tag| tags.insert(tag.to_owned()))
+        } else {
+            source.to_owned()
+        };
+        let mut verbatim = page::Placeholders::new("orestatyverbatim");
+        let (events, toc) = self.heading_ids(self.markdown_parser(&source).collect(), &tags);
+        let events = self.render_events(events.into_iter(), page, &tags, &mut verbatim)?;
         let mut content = String::new();
-        let (events, metadata) = {
-            let mut accumulating_metadata = false;
-            let mut metadata = String::new();
-            let events = events
-                .into_iter()
-                .inspect(|event| {
-                    use pulldown_cmark::{Event, MetadataBlockKind::YamlStyle, Tag, TagEnd};
-                    match &event {
-                        Event::Start(Tag::MetadataBlock(YamlStyle)) => accumulating_metadata = true,
-                        Event::End(TagEnd::MetadataBlock(YamlStyle)) => {
-                            accumulating_metadata = false
-                        }
-                        Event::Text(text) => {
-                            if accumulating_metadata {
-                                metadata.push_str(text);
+        pulldown_cmark::html::push_html(&mut content, events.into_iter());
+        let content = if templating {
+            tags.restore(&content)
+        } else {
+            let content = verbatim.restore(&content);
+            verbatim.insert(content)
+        };
+
+        let template = metadata
+            .template
+            .clone()
+            .unwrap_or_else(|| self.config.default_markdown_template.clone());
+        let page_data = page::PageData {
+            path: relative_path,
+            url: &page.url,
+            extra: &metadata.extra,
+            toc: &toc,
+            info: &info,
+        };
+        let params = serde_json::json!({
+            "metadata": metadata,
+            "path": relative_path,
+            "toc": toc_html(&toc),
+        });
+        match with_template {
+            true => self.render_html(&template, &content, page_data, params),
+            false => self.render_content(&content, page_data, params),
+        }
+        .map(|html| verbatim.restore(&html))
+        .map_err(|diagnostic| diagnostic.or_in_file(src))
+    }
+
+    /// Give all headings an id (slug of their text without Handlebars tags, unique within the
+    /// page), adding self-link anchors if [`Config::heading_anchors`] is set. Returns the table
+    /// of contents
+    fn heading_ids<'e>(
+        &self,
+        events: Vec<pulldown_cmark::Event<'e>>,
+        tags: &page::Placeholders,
+    ) -> (Vec<pulldown_cmark::Event<'e>>, Vec<TocEntry>) {
+        use pulldown_cmark::{Event, Tag, TagEnd};
+
+        let mut ids = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Start(Tag::Heading { id: Some(id), .. }) => Some(id.to_string()),
+                _ => None,
+            })
+            .collect::<std::collections::HashSet<_>>();
+
+        let mut toc = Vec::new();
+        let mut output = Vec::with_capacity(events.len());
+        // Index of the heading start event in the output and it's text
+        let mut heading = None;
+        for event in events {
+            match &event {
+                Event::Start(Tag::Heading { .. }) => heading = Some((output.len(), String::new())),
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, title)) = &mut heading {
+                        title.push_str(text);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    let Some((start, title)) = heading.take() else {
+                        unreachable!("Heading ends without starting! Buggy build!");
+                    };
+                    let title = tags.restore_with(&title, tag_text).trim().to_owned();
+                    let Event::Start(Tag::Heading { level, id, .. }) = &mut output[start] else {
+                        unreachable!("Heading start is not a heading! Buggy build!");
+                    };
+                    let id = match id {
+                        Some(id) => id.to_string(),
+                        None => {
+                            let slug = match text::slugify(&title) {
+                                slug if slug.is_empty() => "heading".to_owned(),
+                                slug => slug,
+                            };
+                            let mut unique = slug.clone();
+                            let mut index = 0;
+                            while ids.contains(&unique) {
+                                index += 1;
+                                unique = format!("{}-{}", slug, index);
                             }
+                            ids.insert(unique.clone());
+                            *id = Some(unique.clone().into());
+                            unique
                         }
-                        _ => (),
+                    };
+                    insert_toc_entry(
+                        &mut toc,
+                        TocEntry {
+                            level: *level as usize,
+                            id: id.clone(),
+                            title,
+                            children: Vec::new(),
+                        },
+                    );
+                    if self.config.heading_anchors {
+                        output.push(Event::InlineHtml(
+                            format!(
+                                " <a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>",
+                                handlebars::html_escape(&id)
+                            )
+                            .into(),
+                        ));
                     }
-                })
-                .collect::<Vec<_>>();
-            (
-                events,
-                self.unwrap_or_error(
-                    serde_yml::from_str::<Metadata>(&metadata),
-                    "Invalid metadata format",
-                )
-                .unwrap_or_default(),
-            )
+                }
+                _ => (),
+            }
+            output.push(event);
+        }
+        (output, toc)
+    }
+
+    /// Render inline code and code blocks (with syntax highlighting), replacing them with
+    /// placeholders. Handlebars tags in code are restored as text.
+    /// Links, images, headings and code blocks are rendered through render hooks
+    /// (`render.link`, `render.image`, `render.heading` and `render.codeblock` templates) if
+    /// they are registered
+    fn render_events<'e>(
+        &self,
+        events: impl Iterator<Item = pulldown_cmark::Event<'e>>,
+        page: &site::Source,
+        tags: &page::Placeholders,
+        verbatim: &mut page::Placeholders,
+    ) -> BuildResult<Vec<pulldown_cmark::Event<'e>>> {
+        use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+        let mut rendered = Vec::new();
+        // Elements being rendered through hooks with their events, including the start one
+        let mut elements: Vec<(Hook, Vec<Event<'e>>)> = Vec::new();
+        for event in events {
+            let hook = match &event {
+                Event::Start(Tag::CodeBlock(kind)) => Some(Hook::CodeBlock {
+                    lang: match kind {
+                        CodeBlockKind::Fenced(info) => {
+                            info.split_whitespace().next().map(str::to_owned)
+                        }
+                        CodeBlockKind::Indented => None,
+                    },
+                }),
+                Event::Start(Tag::Link {
+                    dest_url, title, ..
+                }) if self.handlebars.has_template("render.link") => Some(Hook::Link {
+                    href: dest_url.to_string(),
+                    title: title.to_string(),
+                }),
+                Event::Start(Tag::Image {
+                    dest_url, title, ..
+                }) if self.handlebars.has_template("render.image") => Some(Hook::Image {
+                    href: dest_url.to_string(),
+                    title: title.to_string(),
+                }),
+                Event::Start(Tag::Heading { level, id, .. })
+                    if self.handlebars.has_template("render.heading") =>
+                {
+                    Some(Hook::Heading {
+                        level: *level as usize,
+                        id: id.as_ref().map(|id| id.to_string()),
+                    })
+                }
+                _ => None,
+            };
+            if let Some(hook) = hook {
+                elements.push((hook, vec![event]));
+                continue;
+            }
+
+            let event = match (event, elements.last_mut()) {
+                (Event::End(end), Some((hook, _))) if hook.ends_with(&end) => {
+                    let Some((hook, mut element)) = elements.pop() else {
+                        unreachable!("Element ends without starting! Buggy build!");
+                    };
+                    element.push(Event::End(end));
+                    self.render_hook(hook, element, page, verbatim)?
+                }
+                (Event::Text(text), Some((Hook::CodeBlock { .. }, _))) => {
+                    Event::Text(tags.restore(&text).into())
+                }
+                (Event::Code(text), _) => {
+                    let mut html = String::new();
+                    pulldown_cmark::html::push_html(
+                        &mut html,
+                        std::iter::once(Event::Code(tags.restore(&text).into())),
+                    );
+                    Event::InlineHtml(verbatim.insert(html).into())
+                }
+                (event, _) => event,
+            };
+            match elements.last_mut() {
+                Some((_, element)) => element.push(event),
+                None => rendered.push(event),
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Render an element (all of it's events, including start and end) through it's render hook
+    fn render_hook<'e>(
+        &self,
+        hook: Hook,
+        element: Vec<pulldown_cmark::Event<'e>>,
+        page: &site::Source,
+        verbatim: &mut page::Placeholders,
+    ) -> BuildResult<pulldown_cmark::Event<'e>> {
+        use pulldown_cmark::Event;
+
+        let inner = &element[1..element.len() - 1];
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, inner.iter().cloned());
+        let text = inner
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        let render = |template: &str, params: serde_json::Value| {
+            self.handlebars.render(template, &params).map_err(|err| {
+                self.in_template(Diagnostic::from_render(&err), template, &page.relative_path)
+            })
+        };
+        Ok(match hook {
+            Hook::Link { href, title } => Event::InlineHtml(
+                render(
+                    "render.link",
+                    serde_json::json!({ "href": href, "title": title, "text": html }),
+                )?
+                .into(),
+            ),
+            Hook::Image { href, title } => Event::InlineHtml(
+                render(
+                    "render.image",
+                    serde_json::json!({ "href": href, "title": title, "text": text }),
+                )?
+                .into(),
+            ),
+            Hook::Heading { level, id } => Event::Html(
+                render(
+                    "render.heading",
+                    serde_json::json!({ "level": level, "id": id, "text": html }),
+                )?
+                .into(),
+            ),
+            Hook::CodeBlock { lang } => {
+                let mut highlighted = String::new();
+                pulldown_cmark::html::push_html(
+                    &mut highlighted,
+                    self.syntax_highlighting
+                        .highlight_markdown(element, |diagnostic| {
+                            self.report(diagnostic.in_file(&page.path));
+                        }),
+                );
+                let html = if self.handlebars.has_template("render.codeblock") {
+                    render(
+                        "render.codeblock",
+                        serde_json::json!({
+                            "lang": lang,
+                            "code": text,
+                            "highlighted": highlighted,
+                        }),
+                    )?
+                } else {
+                    highlighted
+                };
+                Event::Html(verbatim.insert(html).into())
+            }
+        })
+    }
+
+    /// Get [`OreStaty::markdown_options`] for parsing. Front matter is parsed separately (see
+    /// [`front_matter::split`]), so metadata blocks are disabled
+    pub fn markdown_parser_options(&self) -> pulldown_cmark::Options {
+        let mut options = self.markdown_options;
+        options.remove(
+            pulldown_cmark::Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+                | pulldown_cmark::Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS,
+        );
+        options
+    }
+
+    /// Create a markdown parser, see [`Self::markdown_parser_options`]
+    pub fn markdown_parser<'s>(&self, source: &'s str) -> pulldown_cmark::Parser<'s> {
+        pulldown_cmark::Parser::new_ext(source, self.markdown_parser_options())
+    }
+
+    /// Collect metadata and content information of a markdown page, see
+    /// [`Self::content_info`]. Invalid metadata is ignored
+    pub fn index_markdown(&self, source: &str) -> (Metadata, ContentInfo) {
+        let (metadata, source, _) = front_matter::split(source);
+        let metadata =
+            front_matter::FrontMatter::parse_or_default::<Metadata>(metadata).unwrap_or_default();
+        let info = self.content_info(source, metadata.templating.unwrap_or(true));
+        (metadata, info)
+    }
+
+    /// Collect summary (content before `<!-- more -->`, or the first paragraph), word count and
+    /// reading time of markdown content (without front matter). With templating, Handlebars
+    /// tags are left out, escaped tags are kept as text. Tags in code are kept as written
+    pub fn content_info(&self, source: &str, templating: bool) -> ContentInfo {
+        use pulldown_cmark::{Event, Tag, TagEnd};
+
+        // Tags are replaced with placeholders, like in `render_markdown`, so that markdown
+        // tells which of them are in code
+        let mut tags = page::Placeholders::new("orestatyhbs");
+        let source = match templating {
+            true => replace_tags(source, |tag| tags.insert(tag.to_owned())),
+            false => source.to_owned(),
+        };
+        let mut in_code_block = false;
+        let events = self
+            .markdown_parser(&source)
+            .map(|event| match event {
+                Event::Start(Tag::CodeBlock(_)) => {
+                    in_code_block = true;
+                    event
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code_block = false;
+                    event
+                }
+                Event::Text(text) if in_code_block => Event::Text(tags.restore(&text).into()),
+                Event::Code(code) => Event::Code(tags.restore(&code).into()),
+                Event::Text(text) => Event::Text(tags.restore_with(&text, tag_text).into()),
+                Event::Html(html) => Event::Html(tags.restore_with(&html, tag_text).into()),
+                Event::InlineHtml(html) => {
+                    Event::InlineHtml(tags.restore_with(&html, tag_text).into())
+                }
+                event => event,
+            })
+            .collect::<Vec<_>>();
+        let more = events.iter().position(|event| match event {
+            Event::Html(html) => html
+                .trim()
+                .strip_prefix("<!--")
+                .and_then(|comment| comment.strip_suffix("-->"))
+                .is_some_and(|comment| comment.trim() == "more"),
+            _ => false,
+        });
+        let summary = match more {
+            Some(more) => &events[..more],
+            None => {
+                let start = events
+                    .iter()
+                    .position(|event| matches!(event, Event::Start(Tag::Paragraph)));
+                let end = start.and_then(|start| {
+                    events[start..]
+                        .iter()
+                        .position(|event| matches!(event, Event::End(TagEnd::Paragraph)))
+                        .map(|end| start + end + 1)
+                });
+                match (start, end) {
+                    (Some(start), Some(end)) => &events[start..end],
+                    _ => &[],
+                }
+            }
         };
 
-        let syntax_highlighting = self.syntax_highlighting.try_lock().unwrap();
-        let events = syntax_highlighting.highlight_markdown(events);
-        pulldown_cmark::html::push_html(&mut content, events);
-        drop(syntax_highlighting);
-        self.render_html(
-            &self.config.default_markdown_template.clone(),
-            &content,
-            page::PageData {
-                path: relative_path,
-            },
-            serde_json::json!({
-                "metadata": metadata,
-                "path": relative_path,
-            }),
-        )
+        let mut text = String::new();
+        for event in summary {
+            match event {
+                Event::Text(part) | Event::Code(part) => text.push_str(part),
+                Event::End(
+                    TagEnd::Emphasis
+                    | TagEnd::Strong
+                    | TagEnd::Strikethrough
+                    | TagEnd::Link
+                    | TagEnd::Image,
+                ) => (),
+                Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+                _ => (),
+            }
+        }
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(
+            &mut html,
+            // Errors are reported when the page is built
+            self.syntax_highlighting
+                .highlight_markdown(summary.iter().cloned(), |_| ()),
+        );
+
+        let word_count = events
+            .iter()
+            .map(|event| match event {
+                Event::Text(text) | Event::Code(text) => text
+                    .split_whitespace()
+                    .filter(|word| word.chars().any(char::is_alphanumeric))
+                    .count(),
+                _ => 0,
+            })
+            .sum::<usize>();
+        ContentInfo {
+            summary: Some(text).filter(|text| !text.is_empty()),
+            summary_html: Some(html).filter(|html| !html.trim().is_empty()),
+            word_count,
+            reading_time: word_count.div_ceil(WORDS_PER_MINUTE),
+        }
     }
 
     /// Register default markdown template
@@ -78,3 +534,191 @@ impl OreStaty<'_> {
             .expect("Failed to register default markdown template! Buggy build");
     }
 }
+
+/// Add an entry to the table of contents, nesting it into the last entry of a higher level
+fn insert_toc_entry(toc: &mut Vec<TocEntry>, entry: TocEntry) {
+    match toc.last_mut() {
+        Some(last) if last.level < entry.level => insert_toc_entry(&mut last.children, entry),
+        _ => toc.push(entry),
+    }
+}
+
+/// Render table of contents as nested lists of links, sent to template as `toc`
+fn toc_html(toc: &[TocEntry]) -> String {
+    if toc.is_empty() {
+        return String::new();
+    }
+    let mut html = String::from("<ul>");
+    for entry in toc {
+        html.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>{}</li>",
+            handlebars::html_escape(&entry.id),
+            handlebars::html_escape(&entry.title),
+            toc_html(&entry.children)
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Markdown element, rendered through a render hook, see [`OreStaty::render_events`]
+enum Hook {
+    Link { href: String, title: String },
+    Image { href: String, title: String },
+    Heading { level: usize, id: Option<String> },
+    CodeBlock { lang: Option<String> },
+}
+
+impl Hook {
+    /// Check if the tag ends this element
+    fn ends_with(&self, end: &pulldown_cmark::TagEnd) -> bool {
+        use pulldown_cmark::TagEnd;
+        matches!(
+            (self, end),
+            (Self::Link { .. }, TagEnd::Link)
+                | (Self::Image { .. }, TagEnd::Image)
+                | (Self::Heading { .. }, TagEnd::Heading(_))
+                | (Self::CodeBlock { .. }, TagEnd::CodeBlock)
+        )
+    }
+}
+
+/// Replace all Handlebars tags (`{{...}}`, `{{{...}}}`, `{{!-- ... --}}` and escaped `\{{...}}`)
+/// in the source
+fn replace_tags(source: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    let mut protected = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(mut start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            start -= 1;
+        }
+        let tag = rest[start..].trim_start_matches('\\');
+        let close = if tag.starts_with("{{!--") {
+            "--}}"
+        } else if tag.starts_with("{{{{") {
+            "}}}}"
+        } else if tag.starts_with("{{{") {
+            "}}}"
+        } else {
+            "}}"
+        };
+        let Some(end) = rest[start..].find(close) else {
+            break;
+        };
+        let end = start + end + close.len();
+        protected.push_str(&rest[..start]);
+        protected.push_str(&replace(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    protected.push_str(rest);
+    protected
+}
+
+/// Processor for Markdown pages, see [`OreStaty::build_markdown`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MarkdownProcessor;
+
+impl processors::FileProcessor for MarkdownProcessor {
+    fn output_extension(&self) -> &str {
+        "html"
+    }
+
+    fn build(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> BuildResult<(String, cache::Dependencies)> {
+        let built = generator.build_markdown(source)?;
+        Ok((built, generator.page_dependencies(&source.path)))
+    }
+
+    fn index(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> Option<(Metadata, ContentInfo)> {
+        let markdown = std::fs::read_to_string(&source.path).ok()?;
+        Some(generator.index_markdown(&markdown))
+    }
+
+    fn content(&self, generator: &OreStaty, source: &site::Source) -> BuildResult<String> {
+        generator.markdown_content(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(source: &str) -> String {
+        replace_tags(source, |tag| format!("[{}]", tag))
+    }
+
+    #[test]
+    fn tags_replaced() {
+        assert_eq!(tags("a {{b}} c {{ d }}"), "a [{{b}}] c [{{ d }}]");
+        assert_eq!(tags("{{#if x}}y{{/if}}"), "[{{#if x}}]y[{{/if}}]");
+        assert_eq!(tags("no tags { here }"), "no tags { here }");
+    }
+
+    #[test]
+    fn triple_braces() {
+        assert_eq!(tags("{{{ content }}} }}"), "[{{{ content }}}] }}");
+    }
+
+    #[test]
+    fn escaped() {
+        assert_eq!(tags(r"a \{{b}} {{c}}"), r"a [\{{b}}] [{{c}}]");
+        assert_eq!(tags(r"\{{{b}}}"), r"[\{{{b}}}]");
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(tags("{{!-- {{a}} }} --}}b"), "[{{!-- {{a}} }} --}}]b");
+        assert_eq!(tags("{{! short }}"), "[{{! short }}]");
+    }
+
+    #[test]
+    fn raw_blocks() {
+        assert_eq!(
+            tags("{{{{raw}}}} {{x}} {{{{/raw}}}}"),
+            "[{{{{raw}}}}] [{{x}}] [{{{{/raw}}}}]"
+        );
+    }
+
+    #[test]
+    fn unclosed() {
+        assert_eq!(tags("a {{b c"), "a {{b c");
+        assert_eq!(tags("{{a}} {{b"), "[{{a}}] {{b");
+        assert_eq!(tags("{{{a}} b"), "{{{a}} b");
+        assert_eq!(tags("{{!-- a }}"), "{{!-- a }}");
+    }
+
+    #[test]
+    fn summary_keeps_tags_in_code() {
+        let info = OreStaty::default().content_info(
+            concat!(
+                "Intro with `{{ inline }}` and {{ tag }}\\{{ escaped }}.\n\n",
+                "```\n{{ block }}\n```\n\n<!-- more -->\n\nRest\n",
+            ),
+            true,
+        );
+        assert_eq!(
+            info.summary.as_deref(),
+            Some("Intro with {{ inline }} and {{ escaped }}. {{ block }}")
+        );
+        let html = info.summary_html.unwrap();
+        assert!(html.contains("<code>{{ inline }}</code>"), "{}", html);
+        assert!(html.contains("{{ block }}"), "{}", html);
+        assert!(!html.contains("tag"), "{}", html);
+        assert_eq!(info.word_count, 7);
+    }
+
+    #[test]
+    fn tag_text_in_plain_text() {
+        assert_eq!(
+            replace_tags(r"Hello {{ name }} \{{ x }}", tag_text),
+            "Hello  {{ x }}"
+        );
+    }
+}
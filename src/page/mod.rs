@@ -1,62 +1,380 @@
 use super::*;
-use serde::Serialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
 
 /// Information about the processed page that gets sent to template as `page`
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct PageData<'a> {
     /// Path of the page
     pub path: &'a std::path::Path,
+    /// URL of the page, relative to the site root
+    pub url: &'a str,
+    /// Custom front matter fields, see [`markdown::Metadata::extra`]
+    pub extra: &'a serde_json::Map<String, serde_json::Value>,
+    /// Table of contents, empty for pages that are not markdown
+    pub toc: &'a [markdown::TocEntry],
+    /// Summary, word count and reading time, empty for pages that are not markdown
+    #[serde(flatten)]
+    pub info: &'a markdown::ContentInfo,
+}
+
+/// Data, that gets sent to templates, see [`OreStaty::render_html`]
+#[derive(Debug, Serialize)]
+struct Context<'a, T: Serialize> {
+    #[serde(flatten)]
+    params: T,
+    page: PageData<'a>,
+    site: &'a site::Site,
+    data: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+/// Chunks of text, replaced with placeholders, that pass through Markdown and Handlebars
+/// unchanged. Placeholders only consist of ASCII letters and digits, like `orestatyverbatim0x`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Placeholders {
+    prefix: &'static str,
+    chunks: Vec<String>,
+}
+
+impl Placeholders {
+    /// Create placeholders with the given prefix. Prefix must be unique and only contain ASCII
+    /// letters
+    pub fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Store a chunk, returning it's placeholder
+    pub fn insert(&mut self, chunk: String) -> String {
+        self.chunks.push(chunk);
+        format!("{}{}x", self.prefix, self.chunks.len() - 1)
+    }
+
+    /// Replace all placeholders in the text with their chunks
+    pub fn restore(&self, text: &str) -> String {
+        self.restore_with(text, str::to_owned)
+    }
+
+    /// Replace all placeholders in the text with their chunks, mapped by `restore`
+    pub fn restore_with(&self, text: &str, mut restore: impl FnMut(&str) -> String) -> String {
+        if self.chunks.is_empty() {
+            return text.to_owned();
+        }
+        let mut restored = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(self.prefix) {
+            restored.push_str(&rest[..start]);
+            rest = &rest[start + self.prefix.len()..];
+            let digits = rest
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let chunk = rest[..digits]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| self.chunks.get(index))
+                .filter(|_| rest[digits..].starts_with('x'));
+            match chunk {
+                Some(chunk) => {
+                    restored.push_str(&restore(chunk));
+                    rest = &rest[digits + 1..];
+                }
+                None => restored.push_str(self.prefix),
+            }
+        }
+        restored.push_str(rest);
+        restored
+    }
+}
+
+/// Front matter of a template
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TemplateMetadata {
+    /// Template, that wraps the output of this one as it's `content`
+    pub layout: Option<String>,
+}
+
+/// Template loaded from a file
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TemplateInfo {
+    /// File the template was loaded from
+    pub path: std::path::PathBuf,
+    /// Number of front matter lines before the template
+    pub line_offset: usize,
+    /// Template metadata
+    pub metadata: TemplateMetadata,
+    /// Does the template mention `pages`, so it might read the site index? See
+    /// [`OreStaty::uses_site_index`]
+    pub uses_site_index: bool,
 }
 
 impl OreStaty<'_> {
-    /// Render HTML template using Handlebars
+    /// Register a Handlebars template from a file. Template can start with front matter (see
+    /// [`TemplateMetadata`] and [`front_matter::split`]), for example to set it's layout
+    pub fn register_template_file(&mut self, name: &str, path: &Path) -> BuildResult<()> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| Diagnostic::io("Failed to read template", err).in_file(path))?;
+        let (metadata, template, line_offset) = front_matter::split(&source);
+        let metadata = front_matter::FrontMatter::parse_or_default::<TemplateMetadata>(metadata)
+            .map_err(|diagnostic| diagnostic.in_file(path))?;
+        self.handlebars
+            .register_template_string(name, template)
+            .map_err(|err| {
+                Diagnostic::from_template(&err)
+                    .offset_lines(line_offset)
+                    .in_file(path)
+            })?;
+        self.templates.insert(
+            name.to_owned(),
+            TemplateInfo {
+                path: path.to_owned(),
+                line_offset,
+                metadata,
+                uses_site_index: template.contains("pages"),
+            },
+        );
+        Ok(())
+    }
+
+    /// Render HTML template using Handlebars, wrapping it into the template layouts.
+    /// Errors in the content have no file set
     pub fn render_html<T: Serialize>(
-        &mut self,
+        &self,
         template: &str,
         content: &str,
         page_data: PageData,
         params: T,
-    ) -> Result<String, ()> {
-        #[derive(Debug, Serialize)]
-        struct Page<'a, T: Serialize> {
-            #[serde(flatten)]
-            params: T,
-            page: PageData<'a>,
-        }
-
+    ) -> BuildResult<String> {
         #[derive(Debug, Serialize)]
         struct PageWithContent<'a, T: Serialize> {
             #[serde(flatten)]
-            page: Page<'a, T>,
+            page: Context<'a, T>,
             content: String,
         }
 
-        let page = Page {
+        let path = page_data.path;
+        let page = self.context(page_data, params);
+        let content = self
+            .handlebars
+            .render_template(content, &page)
+            .map_err(|err| Diagnostic::from_render(&err))?;
+        let mut page = PageWithContent { page, content };
+
+        let mut template = template;
+        let mut chain = vec![template];
+        loop {
+            if !self.handlebars.has_template(template) {
+                let diagnostic = Diagnostic::error(
+                    diagnostic::Code::Template,
+                    format!("Template {:?} is not registered", template),
+                );
+                return Err(match chain.len() {
+                    1 => diagnostic,
+                    _ => self.in_template(diagnostic, chain[chain.len() - 2], path),
+                });
+            }
+
+            let rendered = self.handlebars.render(template, &page).map_err(|err| {
+                let template = err.template_name.as_deref().unwrap_or(template);
+                self.in_template(Diagnostic::from_render(&err), template, path)
+            })?;
+            let Some(layout) = self
+                .templates
+                .get(template)
+                .and_then(|info| info.metadata.layout.as_deref())
+            else {
+                return Ok(rendered);
+            };
+            if chain.contains(&layout) {
+                chain.push(layout);
+                return Err(self.in_template(
+                    Diagnostic::error(
+                        diagnostic::Code::Template,
+                        format!("Layout cycle: {}", chain.join(" -> ")),
+                    ),
+                    template,
+                    path,
+                ));
+            }
+            page.content = rendered;
+            template = layout;
+            chain.push(template);
+        }
+    }
+
+    /// Render HTML content using Handlebars, without any template. Errors have no file set
+    pub fn render_content<T: Serialize>(
+        &self,
+        content: &str,
+        page_data: PageData,
+        params: T,
+    ) -> BuildResult<String> {
+        self.handlebars
+            .render_template(content, &self.context(page_data, params))
+            .map_err(|err| Diagnostic::from_render(&err))
+    }
+
+    /// Data, that gets sent to templates
+    fn context<'a, T: Serialize>(&'a self, page: PageData<'a>, params: T) -> Context<'a, T> {
+        Context {
             params,
-            page: page_data,
-        };
-        let content = self.unwrap_or_error(
-            self.handlebars.render_template(content, &page),
-            "Failed to render page using Handlebars",
-        )?;
-        let page = PageWithContent { page, content };
-        self.unwrap_or_error(
-            self.handlebars.render(template, &page),
-            format!("Failed to render page using template {:?}", template),
-        )
-    }
-
-    /// Build an HTML page from the file using Handlebars
-    pub fn build_page(&mut self, src: &Path, relative_path: &Path) -> Result<String, ()> {
-        let source = self.unwrap_or_error(std::fs::read_to_string(src), "Failed to read file")?;
-        self.render_html(
-            &self.config.default_template.clone(),
-            &source,
-            PageData {
-                path: relative_path,
+            page,
+            site: &self.site,
+            data: &self.data,
+        }
+    }
+
+    /// Point the diagnostic to the file of the template, if it was loaded from one
+    pub(crate) fn in_template(
+        &self,
+        diagnostic: Diagnostic,
+        template: &str,
+        path: &Path,
+    ) -> Diagnostic {
+        match self.templates.get(template) {
+            Some(info) => Diagnostic {
+                message: format!("{} (rendering {:?})", diagnostic.message, path),
+                ..diagnostic
+                    .offset_lines(info.line_offset)
+                    .in_file(&info.path)
             },
-            (),
-        )
+            None => Diagnostic {
+                message: format!("In template {:?}: {}", template, diagnostic.message),
+                ..diagnostic
+            },
+        }
+    }
+
+    /// Build an HTML page from the source using Handlebars. The page can start with front
+    /// matter, see [`front_matter::split`]
+    pub fn build_page(&self, page: &site::Source) -> BuildResult<String> {
+        self.render_page(page, true)
+    }
+
+    /// Render the content of an HTML page, without it's template, see [`Self::build_page`]
+    pub fn page_content(&self, page: &site::Source) -> BuildResult<String> {
+        self.render_page(page, false)
+    }
+
+    fn render_page(&self, page: &site::Source, with_template: bool) -> BuildResult<String> {
+        let src = &page.path;
+        let source = std::fs::read_to_string(src)
+            .map_err(|err| Diagnostic::io("Failed to read file", err).in_file(src))?;
+        let (metadata, source, line_offset) = front_matter::split(&source);
+        let metadata = front_matter::FrontMatter::parse_or_default::<markdown::Metadata>(metadata)
+            .map_err(|diagnostic| diagnostic.in_file(src))?;
+        let template = metadata
+            .template
+            .as_deref()
+            .unwrap_or(&self.config.default_template);
+        let mut verbatim = Placeholders::new("orestatyverbatim");
+        let content = match metadata.templating {
+            Some(false) => verbatim.insert(source.to_owned()),
+            _ => source.to_owned(),
+        };
+        let page_data = PageData {
+            path: &page.relative_path,
+            url: &page.url,
+            extra: &metadata.extra,
+            toc: &[],
+            info: &markdown::ContentInfo::default(),
+        };
+        let params = serde_json::json!({ "metadata": metadata });
+        match with_template {
+            true => self.render_html(template, &content, page_data, params),
+            false => self.render_content(&content, page_data, params),
+        }
+        .map(|html| verbatim.restore(&html))
+        .map_err(|diagnostic| match diagnostic.file {
+            Some(_) => diagnostic,
+            None => diagnostic.offset_lines(line_offset).in_file(src),
+        })
+    }
+
+    /// Collect front matter of an HTML page. Invalid front matter is ignored
+    pub fn index_page(&self, source: &str) -> markdown::Metadata {
+        front_matter::FrontMatter::parse_or_default(front_matter::split(source).0)
+            .unwrap_or_default()
+    }
+}
+
+/// Processor for Handlebars pages, see [`OreStaty::build_page`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PageProcessor;
+
+impl processors::FileProcessor for PageProcessor {
+    fn output_extension(&self) -> &str {
+        "html"
+    }
+
+    fn build(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> BuildResult<(String, cache::Dependencies)> {
+        let built = generator.build_page(source)?;
+        Ok((built, generator.page_dependencies(&source.path)))
+    }
+
+    fn index(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> Option<(markdown::Metadata, markdown::ContentInfo)> {
+        let page = std::fs::read_to_string(&source.path).ok()?;
+        Some((
+            generator.index_page(&page),
+            markdown::ContentInfo::default(),
+        ))
+    }
+
+    fn content(&self, generator: &OreStaty, source: &site::Source) -> BuildResult<String> {
+        generator.page_content(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore() {
+        let mut placeholders = Placeholders::new("orestatytest");
+        let a = placeholders.insert("{{ a }}".to_owned());
+        let b = placeholders.insert("{{ b }}".to_owned());
+        assert_eq!(
+            placeholders.restore(&format!("<p>{} and {}{}</p>", a, b, a)),
+            "<p>{{ a }} and {{ b }}{{ a }}</p>"
+        );
+    }
+
+    #[test]
+    fn restore_unknown() {
+        let mut placeholders = Placeholders::new("orestatytest");
+        placeholders.insert("{{ a }}".to_owned());
+        for text in [
+            "orestatytest5x",
+            "orestatytest0",
+            "orestatytest0y",
+            "orestatytestx",
+            "orestatytest",
+            "orestaty0x",
+        ] {
+            assert_eq!(placeholders.restore(text), text);
+        }
+        assert_eq!(
+            placeholders.restore("orestatytestorestatytest0x"),
+            "orestatytest{{ a }}"
+        );
+    }
+
+    #[test]
+    fn restore_with() {
+        let mut placeholders = Placeholders::new("orestatytest");
+        let tag = placeholders.insert("{{ a }}".to_owned());
+        assert_eq!(
+            placeholders.restore_with(&format!("Hello {}!", tag), |chunk| chunk.len().to_string()),
+            "Hello 7!"
+        );
     }
 }
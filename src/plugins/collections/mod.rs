@@ -0,0 +1,134 @@
+use handlebars::handlebars_helper;
+use serde_json::Value;
+
+/// Get a field of the value by a dot-separated path, like `metadata.title`. Empty path is the
+/// value itself
+pub fn field<'a>(value: &'a Value, path: &str) -> &'a Value {
+    path.split('.')
+        .filter(|key| !key.is_empty() && *key != "this")
+        .try_fold(value, |value, key| match value {
+            Value::Object(object) => object.get(key),
+            Value::Array(array) => key.parse::<usize>().ok().and_then(|index| array.get(index)),
+            _ => None,
+        })
+        .unwrap_or(&Value::Null)
+}
+
+/// Compare values: numbers by value, strings alphabetically (so ISO dates are sorted by date),
+/// null values last
+pub fn compare(a: &Value, b: &Value) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+/// Items of an array, or values of an object
+fn items(collection: &Value) -> Vec<Value> {
+    match collection {
+        Value::Array(array) => array.clone(),
+        Value::Object(object) => object.values().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+handlebars_helper!(filter_where: |collection: Json, path: str, *args| {
+    let matches = |item: &Value| {
+        let field = field(item, path);
+        match (args.get(2), field) {
+            (None, field) => truthy(field),
+            (Some(value), Value::Array(array)) => array.contains(value),
+            (Some(value), field) => field == *value,
+        }
+    };
+    Value::Array(items(collection).into_iter().filter(matches).collect())
+});
+
+handlebars_helper!(sort_by: |collection: Json, path: str, {order: str = "asc"}| {
+    let mut items = items(collection);
+    items.sort_by(|a, b| {
+        let (a, b) = (field(a, path), field(b, path));
+        // Items without the field stay last in both orders
+        if order == "desc" && !a.is_null() && !b.is_null() {
+            compare(b, a)
+        } else {
+            compare(a, b)
+        }
+    });
+    Value::Array(items)
+});
+
+handlebars_helper!(group_by: |collection: Json, path: str, {date: str = ""}| {
+    let mut groups = Vec::<(Value, Vec<Value>)>::new();
+    for item in items(collection) {
+        let key = field(&item, path);
+        let key = match key.as_str().and_then(crate::text::parse_date) {
+            Some(parsed) if !date.is_empty() => {
+                crate::text::try_to_string(parsed.format(date)).map_or(Value::Null, Value::String)
+            }
+            _ => key.clone(),
+        };
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, items)) => items.push(item),
+            None => groups.push((key, vec![item])),
+        }
+    }
+    Value::Array(
+        groups
+            .into_iter()
+            .map(|(key, items)| serde_json::json!({ "key": key, "items": items }))
+            .collect(),
+    )
+});
+
+handlebars_helper!(limit: |collection: Json, count: u64| {
+    Value::Array(items(collection).into_iter().take(count as usize).collect())
+});
+
+handlebars_helper!(offset: |collection: Json, count: u64| {
+    Value::Array(items(collection).into_iter().skip(count as usize).collect())
+});
+
+handlebars_helper!(first: |collection: Json| {
+    items(collection).into_iter().next().unwrap_or_default()
+});
+
+handlebars_helper!(last: |collection: Json| {
+    items(collection).into_iter().next_back().unwrap_or_default()
+});
+
+handlebars_helper!(reverse: |collection: Json| {
+    Value::Array(items(collection).into_iter().rev().collect())
+});
+
+/// Register collection helpers
+pub fn register(handlebars: &mut handlebars::Handlebars) {
+    handlebars.register_helper("where", Box::new(filter_where));
+    handlebars.register_helper("sort_by", Box::new(sort_by));
+    handlebars.register_helper("group_by", Box::new(group_by));
+    handlebars.register_helper("limit", Box::new(limit));
+    handlebars.register_helper("offset", Box::new(offset));
+    handlebars.register_helper("first", Box::new(first));
+    handlebars.register_helper("last", Box::new(last));
+    handlebars.register_helper("reverse", Box::new(reverse));
+}
@@ -3,6 +3,11 @@ use super::*;
 /// Syntax highlighting
 pub mod syntax_highlighting;
 
+/// Collection helpers: where, sort_by, group_by, limit, offset, first, last and reverse
+mod collections;
+/// String, number and date helpers
+mod strings;
+
 impl OreStaty<'_> {
     /// Register built-in plugins
     pub fn register_builtin_plugins(&mut self) {
@@ -10,7 +15,11 @@ impl OreStaty<'_> {
             "highlight",
             Box::new(syntax_highlighting::HighlightHelper(
                 self.syntax_highlighting.clone(),
+                self.diagnostics.clone(),
             )),
         );
+        collections::register(&mut self.handlebars);
+        let markdown_options = self.markdown_parser_options();
+        strings::register(&mut self.handlebars, markdown_options);
     }
 }
@@ -0,0 +1,215 @@
+use handlebars::{handlebars_helper, RenderErrorReason, ScopedJson};
+use serde_json::Value;
+
+/// Text of a value: strings as is, null as an empty string, everything else as JSON
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+handlebars_helper!(slugify: |value: Json| crate::text::slugify(&text(value)));
+
+handlebars_helper!(truncate: |value: Json, length: u64, {end: str = "…"}| {
+    let value = text(value);
+    match value.char_indices().nth(length as usize) {
+        Some((index, _)) => format!("{}{}", value[..index].trim_end(), end),
+        None => value,
+    }
+});
+
+handlebars_helper!(truncate_words: |value: Json, count: u64, {end: str = "…"}| {
+    let value = text(value);
+    let words = value.split_whitespace().collect::<Vec<_>>();
+    if words.len() > count as usize {
+        format!("{}{}", words[..count as usize].join(" "), end)
+    } else {
+        words.join(" ")
+    }
+});
+
+handlebars_helper!(upper: |value: Json| text(value).to_uppercase());
+
+handlebars_helper!(lower: |value: Json| text(value).to_lowercase());
+
+handlebars_helper!(title_case: |value: Json| {
+    let mut title = String::new();
+    let mut word_start = true;
+    for ch in text(value).chars() {
+        if word_start {
+            title.extend(ch.to_uppercase());
+        } else {
+            title.push(ch);
+        }
+        word_start = ch.is_whitespace() || ch == '-';
+    }
+    title
+});
+
+handlebars_helper!(json: |value: Json, {pretty: bool = false}| {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .unwrap_or_default()
+});
+
+handlebars_helper!(join: |values: Json, separator: str| {
+    match values {
+        Value::Array(values) => values.iter().map(text).collect::<Vec<_>>().join(separator),
+        value => text(value),
+    }
+});
+
+handlebars_helper!(replace: |value: Json, from: str, to: str| text(value).replace(from, to));
+
+handlebars_helper!(number: |value: f64, {decimals: u64 = 0, separator: str = ",", point: str = "."}| {
+    let formatted = format!("{:.*}", decimals as usize, value.abs());
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let mut result = String::new();
+    if value < 0.0 && formatted.chars().any(|ch| ch != '0' && ch != '.') {
+        result.push('-');
+    }
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            result.push_str(separator);
+        }
+        result.push(digit);
+    }
+    if !fraction.is_empty() {
+        result.push_str(point);
+        result.push_str(fraction);
+    }
+    result
+});
+
+/// `date value "format" [timezone="Europe/Berlin"]` helper. Formats a date (see
+/// [`crate::text::parse_date`]) or `"now"` with strftime pattern
+pub struct DateHelper;
+
+impl handlebars::HelperDef for DateHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &handlebars::Helper<'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        use chrono::TimeZone;
+
+        let param = |index, name: &str| {
+            helper
+                .param(index)
+                .and_then(|param| param.value().as_str())
+                .ok_or_else(|| RenderErrorReason::ParamNotFoundForName("date", name.to_owned()))
+        };
+        let (date, format) = (param(0, "date")?, param(1, "format")?);
+        let date = if date == "now" {
+            chrono::Local::now().fixed_offset()
+        } else {
+            chrono::DateTime::parse_from_rfc3339(date.trim())
+                .ok()
+                .or_else(|| {
+                    crate::text::parse_date(date)
+                        .and_then(|date| chrono::Local.from_local_datetime(&date).earliest())
+                        .map(|date| date.fixed_offset())
+                })
+                .ok_or_else(|| RenderErrorReason::Other(format!("Invalid date {:?}", date)))?
+        };
+
+        let formatted = match helper.hash_get("timezone").map(|timezone| timezone.value()) {
+            Some(Value::String(timezone)) => {
+                let timezone = timezone.parse::<chrono_tz::Tz>().map_err(|_| {
+                    RenderErrorReason::Other(format!("Unknown timezone {:?}", timezone))
+                })?;
+                crate::text::try_to_string(date.with_timezone(&timezone).format(format))
+            }
+            Some(_) => {
+                return Err(RenderErrorReason::HashTypeMismatchForName(
+                    "date",
+                    "timezone".to_owned(),
+                    "str".to_owned(),
+                )
+                .into())
+            }
+            None => crate::text::try_to_string(date.format(format)),
+        }
+        .ok_or_else(|| RenderErrorReason::Other(format!("Invalid date format {:?}", format)))?;
+        Ok(ScopedJson::Derived(Value::String(formatted)))
+    }
+}
+
+/// `default value fallback` helper. Returns the fallback if the value is missing, null, an
+/// empty string or an empty array. Unlike other helpers, works with missing values in strict
+/// mode
+pub struct DefaultHelper;
+
+impl handlebars::HelperDef for DefaultHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &handlebars::Helper<'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let fallback = helper.param(1).ok_or_else(|| {
+            RenderErrorReason::ParamNotFoundForName("default", "fallback".to_owned())
+        })?;
+        let value = match helper.param(0).map(|param| param.value()) {
+            None | Some(Value::Null) => fallback.value(),
+            Some(Value::String(string)) if string.is_empty() => fallback.value(),
+            Some(Value::Array(array)) if array.is_empty() => fallback.value(),
+            Some(value) => value,
+        };
+        Ok(ScopedJson::Derived(value.clone()))
+    }
+}
+
+/// `markdownify value` helper. Renders markdown into HTML, use with triple braces
+pub struct MarkdownifyHelper(pub pulldown_cmark::Options);
+
+impl handlebars::HelperDef for MarkdownifyHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &handlebars::Helper<'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, handlebars::RenderError> {
+        let markdown = helper
+            .param(0)
+            .map(|param| text(param.value()))
+            .ok_or_else(|| {
+                RenderErrorReason::ParamNotFoundForName("markdownify", "value".to_owned())
+            })?;
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(
+            &mut html,
+            pulldown_cmark::Parser::new_ext(&markdown, self.0),
+        );
+        Ok(ScopedJson::Derived(Value::String(html)))
+    }
+}
+
+/// Register string and date helpers
+pub fn register(
+    handlebars: &mut handlebars::Handlebars,
+    markdown_options: pulldown_cmark::Options,
+) {
+    handlebars.register_helper("slugify", Box::new(slugify));
+    handlebars.register_helper("truncate", Box::new(truncate));
+    handlebars.register_helper("truncate_words", Box::new(truncate_words));
+    handlebars.register_helper("upper", Box::new(upper));
+    handlebars.register_helper("lower", Box::new(lower));
+    handlebars.register_helper("title_case", Box::new(title_case));
+    handlebars.register_helper("json", Box::new(json));
+    handlebars.register_helper("default", Box::new(DefaultHelper));
+    handlebars.register_helper("join", Box::new(join));
+    handlebars.register_helper("replace", Box::new(replace));
+    handlebars.register_helper("number", Box::new(number));
+    handlebars.register_helper("date", Box::new(DateHelper));
+    handlebars.register_helper("markdownify", Box::new(MarkdownifyHelper(markdown_options)));
+}
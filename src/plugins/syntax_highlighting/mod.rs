@@ -100,15 +100,20 @@ impl SyntaxHighlighting {
         }
     }
 
-    /// Highlight a piece of code with the hint of it's syntax, fallback to it without highlighting
+    /// Highlight a piece of code with the hint of it's syntax, fallback to it without highlighting,
+    /// reporting a warning
     pub fn highlight_or_fallback(
         &self,
         code: &str,
         syntax_hint: Option<&syntect::parsing::SyntaxReference>,
+        report: impl FnOnce(Diagnostic),
     ) -> String {
         self.highlight(code, syntax_hint).unwrap_or_else(|err| {
-            eprintln!("Warning: Failed to highlight: {}", err);
-            format!("<pre><code>{code}</code></pre>")
+            report(Diagnostic::warning(
+                diagnostic::Code::Render,
+                format!("Failed to highlight code: {}", err),
+            ));
+            format!("<pre><code>{}</code></pre>", handlebars::html_escape(code))
         })
     }
 
@@ -129,8 +134,11 @@ impl SyntaxHighlighting {
     }
 }
 
-/// Highlight helper
-pub struct HighlightHelper(pub ArcMutex<SyntaxHighlighting>);
+/// Highlight helper. Code that failed to highlight is reported into the diagnostics
+pub struct HighlightHelper(
+    pub std::sync::Arc<SyntaxHighlighting>,
+    pub std::sync::Arc<std::sync::Mutex<Vec<Diagnostic>>>,
+);
 
 impl handlebars::HelperDef for HighlightHelper {
     fn call<'reg: 'rc, 'rc>(
@@ -156,7 +164,7 @@ impl handlebars::HelperDef for HighlightHelper {
                 "\"code\"".to_owned(),
             ))?;
 
-        let syntax_highlighing = self.0.try_lock().unwrap();
+        let syntax_highlighing = &self.0;
         let syntax_hint = if let Some(syntax) = helper.param(0) {
             let syntax = syntax
                 .value()
@@ -184,16 +192,26 @@ impl handlebars::HelperDef for HighlightHelper {
             None
         };
 
-        output.write(&syntax_highlighing.highlight_or_fallback(&code, syntax_hint))?;
+        output.write(&syntax_highlighing.highlight_or_fallback(
+            &code,
+            syntax_hint,
+            |diagnostic| {
+                self.1
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push(diagnostic)
+            },
+        ))?;
         Ok(())
     }
 }
 
 impl SyntaxHighlighting {
-    /// Highlight all code in this markdown event stream
+    /// Highlight all code in this markdown event stream, reporting code that failed to highlight
     pub fn highlight_markdown<'a, 'e>(
         &'a self,
         events: impl IntoIterator<Item = pulldown_cmark::Event<'e>> + 'a,
+        report: impl Fn(Diagnostic) + 'a,
     ) -> impl Iterator<Item = pulldown_cmark::Event<'e>> + 'a {
         use pulldown_cmark::{Event, Tag, TagEnd};
 
@@ -224,6 +242,7 @@ impl SyntaxHighlighting {
                     self.highlight_or_fallback(
                         &highlighted_code.code,
                         highlighted_code.syntax_hint,
+                        &report,
                     ),
                 )))
             }
@@ -0,0 +1,144 @@
+use super::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Source file processor, that builds one type of sources. Register your own with
+/// [`Processors::register`] to support new source types
+pub trait FileProcessor: Send + Sync {
+    /// Extension of the output file, unless overridden when mapping an extension
+    fn output_extension(&self) -> &str;
+
+    /// Build the source, returning output content and files it depends on
+    fn build(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> BuildResult<(String, cache::Dependencies)>;
+
+    /// Collect metadata and content information (like summary) of the source for the site-wide
+    /// page index. Errors should be ignored here, they are reported when the source is built
+    fn index(
+        &self,
+        _generator: &OreStaty,
+        _source: &site::Source,
+    ) -> Option<(markdown::Metadata, markdown::ContentInfo)> {
+        None
+    }
+
+    /// Render the content of a page without it's template, used for feeds with full content
+    fn content(&self, _generator: &OreStaty, source: &site::Source) -> BuildResult<String> {
+        Err(Diagnostic::error(
+            diagnostic::Code::UnknownSource,
+            "Source has no content to put into feeds",
+        )
+        .in_file(&source.path))
+    }
+}
+
+/// Processor an extension is mapped to
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtensionMapping {
+    /// Processor name
+    pub processor: String,
+    /// Output extension, processor's default if not set
+    pub output: Option<String>,
+}
+
+/// Registry of file processors and extensions they handle
+#[derive(Clone)]
+pub struct Processors {
+    processors: HashMap<String, Arc<dyn FileProcessor>>,
+    extensions: HashMap<String, ExtensionMapping>,
+}
+
+impl std::fmt::Debug for Processors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Processors")
+            .field("processors", &self.processors.keys().collect::<Vec<_>>())
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+impl Default for Processors {
+    fn default() -> Self {
+        let mut processors = Self {
+            processors: HashMap::new(),
+            extensions: HashMap::new(),
+        };
+        processors.register("page", page::PageProcessor);
+        processors.register("markdown", markdown::MarkdownProcessor);
+        processors.register("sass", sass::SassProcessor);
+        for extension in ["html", "htm", "hbs"] {
+            processors.map_extension(extension, "page", None);
+        }
+        for extension in ["md", "markdown"] {
+            processors.map_extension(extension, "markdown", None);
+        }
+        for extension in ["sass", "scss", "css"] {
+            processors.map_extension(extension, "sass", None);
+        }
+        processors
+    }
+}
+
+impl Processors {
+    /// Register a processor, replacing the one with the same name
+    pub fn register(&mut self, name: &str, processor: impl FileProcessor + 'static) {
+        self.processors.insert(name.to_owned(), Arc::new(processor));
+    }
+
+    /// Build files with this extension using the processor with the given name. If output
+    /// extension is not set, processor's default is used
+    pub fn map_extension(&mut self, extension: &str, processor: &str, output: Option<&str>) {
+        self.extensions.insert(
+            extension.to_owned(),
+            ExtensionMapping {
+                processor: processor.to_owned(),
+                output: output.map(str::to_owned),
+            },
+        );
+    }
+
+    /// Get a processor by it's name
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn FileProcessor>> {
+        self.processors.get(name)
+    }
+
+    /// Find the processor for a source file, returning it's name and the output extension
+    pub fn find(&self, src: &Path) -> Option<(&str, String)> {
+        let mapping = self
+            .extensions
+            .get(src.extension()?.to_string_lossy().as_ref())?;
+        let processor = self.processors.get(&mapping.processor)?;
+        Some((
+            &mapping.processor,
+            mapping
+                .output
+                .clone()
+                .unwrap_or_else(|| processor.output_extension().to_owned()),
+        ))
+    }
+}
+
+impl OreStaty<'_> {
+    /// Map extensions from [`Config::extensions`], reporting unknown processors. Called by
+    /// [`OreStaty::build`], after library users registered their processors
+    pub(crate) fn map_config_extensions(&mut self) {
+        for (extension, mapping) in &self.config.extensions {
+            if self.processors.get(&mapping.processor).is_none() {
+                self.report(Diagnostic::error(
+                    diagnostic::Code::Config,
+                    format!(
+                        "Extension {:?} is mapped to unknown processor {:?}",
+                        extension, mapping.processor
+                    ),
+                ));
+                continue;
+            }
+            self.processors
+                .map_extension(extension, &mapping.processor, mapping.output.as_deref());
+        }
+    }
+}
@@ -1,16 +1,84 @@
 use super::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// SASS options, see [`grass::Options`]. Those can't be shared between threads, so they are
+/// created from these for every compilation, with a file system that tracks imported files
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SassOptions {
+    /// Output style
+    pub style: grass::OutputStyle,
+    /// Directories to look for imports in
+    pub load_paths: Vec<PathBuf>,
+    /// Add `@charset` or BOM to the output, if it contains non-ASCII characters
+    pub allows_charset: bool,
+    /// Use unicode characters in error messages
+    pub unicode_error_messages: bool,
+    /// Don't print warnings and `@debug` messages
+    pub quiet: bool,
+}
+
+impl Default for SassOptions {
+    fn default() -> Self {
+        Self {
+            style: grass::OutputStyle::Expanded,
+            load_paths: Vec::new(),
+            allows_charset: true,
+            unicode_error_messages: true,
+            quiet: false,
+        }
+    }
+}
+
+impl SassOptions {
+    /// Create [`grass::Options`], using the given file system
+    pub fn to_grass<'a>(&self, fs: &'a dyn grass::Fs) -> grass::Options<'a> {
+        grass::Options::default()
+            .fs(fs)
+            .style(self.style)
+            .load_paths(&self.load_paths)
+            .allows_charset(self.allows_charset)
+            .unicode_error_messages(self.unicode_error_messages)
+            .quiet(self.quiet)
+    }
+}
 
 impl OreStaty<'_> {
-    /// Build an HTML page from the file using Handlebars
-    pub fn build_sass(
-        &mut self,
-        src: &Path,
-        relative_path: &Path,
-    ) -> Result<String, ()> {
-        self.unwrap_or_error(
-            grass::from_path(src, &self.sass_options),
-            format!("Failed to render {:?} using SASS", relative_path),
-        )
+    /// Build CSS from the file using SASS
+    pub fn build_sass(&self, src: &Path, _relative_path: &Path) -> BuildResult<String> {
+        self.build_sass_tracked(src).map(|(css, _)| css)
+    }
+
+    /// Build CSS from the file using SASS, also returning all imported files
+    pub fn build_sass_tracked(&self, src: &Path) -> BuildResult<(String, cache::Dependencies)> {
+        let tracker = cache::DependencyTracker::default();
+        let options = self.sass_options.to_grass(&tracker);
+        let css = grass::from_path(src, &options)
+            .map_err(|err| Diagnostic::from_sass(*err).or_in_file(src))?;
+        Ok((
+            css,
+            cache::Dependencies {
+                inputs: tracker.into_inputs(),
+                generator: false,
+                site_index: false,
+            },
+        ))
+    }
+}
+
+/// Processor for SASS stylesheets, see [`OreStaty::build_sass`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SassProcessor;
+
+impl processors::FileProcessor for SassProcessor {
+    fn output_extension(&self) -> &str {
+        "css"
+    }
+
+    fn build(
+        &self,
+        generator: &OreStaty,
+        source: &site::Source,
+    ) -> BuildResult<(String, cache::Dependencies)> {
+        generator.build_sass_tracked(&source.path)
     }
 }
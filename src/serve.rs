@@ -0,0 +1,259 @@
+use crate::Diagnostic;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+
+/// URL of the live reload event stream
+pub const LIVE_RELOAD_URL: &str = "/__orestaty/livereload";
+
+/// Script, that gets injected into every served HTML page to reload it after rebuild
+pub const LIVE_RELOAD_SCRIPT: &str = "<script>new EventSource(\"/__orestaty/livereload\").onmessage = () => location.reload();</script>";
+
+/// Development server with live reload. Serves built site from a directory
+pub struct Server {
+    server: Arc<tiny_http::Server>,
+    root: PathBuf,
+    clients: Arc<Mutex<Vec<mpsc::Sender<()>>>>,
+    errors: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+/// Called with errors of serving requests, see [`Server::spawn`]
+pub type ErrorHandler = Arc<dyn Fn(Diagnostic) + Send + Sync>;
+
+impl Server {
+    /// Start a server on the given address, serving files from the given directory
+    pub fn new(
+        address: impl std::net::ToSocketAddrs,
+        root: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            server: Arc::new(tiny_http::Server::http(address)?),
+            root: root.to_owned(),
+            clients: Default::default(),
+            errors: Default::default(),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn address(&self) -> String {
+        self.server.server_addr().to_string()
+    }
+
+    /// Make all open pages reload
+    pub fn reload(&self) {
+        self.clients
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|client| client.send(()).is_ok());
+    }
+
+    /// Set errors of the last build. If there are any, an error page is served instead of the
+    /// site. Warnings are ignored
+    pub fn set_errors(&self, diagnostics: Vec<Diagnostic>) {
+        *self.errors.lock().unwrap_or_else(|err| err.into_inner()) = diagnostics
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .collect();
+    }
+
+    /// Serve requests in a background thread, passing errors of responding to `on_error`
+    pub fn spawn(
+        &self,
+        on_error: impl Fn(Diagnostic) + Send + Sync + 'static,
+    ) -> std::thread::JoinHandle<()> {
+        let handler = Handler {
+            root: self.root.clone(),
+            clients: self.clients.clone(),
+            errors: self.errors.clone(),
+            on_error: Arc::new(on_error),
+        };
+        let server = self.server.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let handler = handler.clone();
+                std::thread::spawn(move || handler.handle(request));
+            }
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Handler {
+    root: PathBuf,
+    clients: Arc<Mutex<Vec<mpsc::Sender<()>>>>,
+    errors: Arc<Mutex<Vec<Diagnostic>>>,
+    on_error: ErrorHandler,
+}
+
+impl Handler {
+    fn handle(&self, request: tiny_http::Request) {
+        let url = request.url().split(['?', '#']).next().unwrap_or_default();
+        let url = percent_decode(url);
+        let result = if url == LIVE_RELOAD_URL {
+            let (sender, receiver) = mpsc::channel();
+            self.clients
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .push(sender);
+            event_stream(request.into_writer(), receiver)
+        } else {
+            let (status, content_type, body) = self.respond(&url);
+            request.respond(
+                tiny_http::Response::from_data(body)
+                    .with_status_code(status)
+                    .with_header(header("Content-Type", content_type))
+                    .with_header(header("Cache-Control", "no-store")),
+            )
+        };
+        if let Err(err) = result {
+            if err.kind() != std::io::ErrorKind::BrokenPipe {
+                (self.on_error)(Diagnostic::io("Failed to respond to request", err));
+            }
+        }
+    }
+
+    fn respond(&self, url: &str) -> (u16, &'static str, Vec<u8>) {
+        let errors = self
+            .errors
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+
+        let path = self.resolve(url);
+        let content_type = path.as_deref().map_or("text/html", content_type);
+        if !errors.is_empty() && content_type == "text/html" {
+            return (500, "text/html", error_page(&errors).into_bytes());
+        }
+
+        let (status, path) = match path {
+            Some(path) => (200, path),
+            None => (404, self.root.join("404.html")),
+        };
+        match std::fs::read(&path) {
+            Ok(content) if content_type == "text/html" => (
+                status,
+                content_type,
+                inject_live_reload(&String::from_utf8_lossy(&content)).into_bytes(),
+            ),
+            Ok(content) => (status, content_type, content),
+            Err(_) => (
+                404,
+                "text/html",
+                inject_live_reload(&format!(
+                    "<h1>404 Not Found</h1><p>{}</p>",
+                    handlebars::html_escape(url)
+                ))
+                .into_bytes(),
+            ),
+        }
+    }
+
+    /// Find file for the URL, using index.html for directories
+    fn resolve(&self, url: &str) -> Option<PathBuf> {
+        let mut path = self.root.clone();
+        for component in url.split('/') {
+            match component {
+                "" | "." => (),
+                ".." => return None,
+                component => path.push(component),
+            }
+        }
+        if path.is_dir() {
+            path.push("index.html");
+        }
+        path.is_file().then_some(path)
+    }
+}
+
+/// Send a server-sent reload event every time the receiver is notified
+fn event_stream(
+    mut writer: impl std::io::Write,
+    receiver: mpsc::Receiver<()>,
+) -> std::io::Result<()> {
+    writer.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\n\r\n",
+    )?;
+    writer.flush()?;
+    while receiver.recv().is_ok() {
+        writer.write_all(b"data: reload\n\n")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn header(field: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(field, value).expect("Invalid header! Buggy build!")
+}
+
+/// Insert live reload script into an HTML page
+pub fn inject_live_reload(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], LIVE_RELOAD_SCRIPT, &html[index..]),
+        None => format!("{}{}", html, LIVE_RELOAD_SCRIPT),
+    }
+}
+
+/// Render page with build errors
+pub fn error_page(errors: &[Diagnostic]) -> String {
+    let errors = errors
+        .iter()
+        .map(|error| format!("<pre>{}</pre>", handlebars::html_escape(&error.to_string())))
+        .collect::<String>();
+    format!(
+        "<!doctype html><html><head><title>Build failed</title><style>{}</style></head><body><h1>Build failed</h1>{}{}</body></html>",
+        "body { font-family: sans-serif; background: #1e1e1e; color: #eee; padding: 1em 2em; } \
+        h1 { color: #ff6b6b; } \
+        pre { background: #2d2d2d; padding: 1em; border-left: 4px solid #ff6b6b; white-space: pre-wrap; }",
+        errors,
+        LIVE_RELOAD_SCRIPT,
+    )
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .map_or(String::new(), |ext| ext.to_string_lossy().to_lowercase())
+        .as_str()
+    {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn percent_decode(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,472 @@
+use super::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A source file found in the source directory
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Source {
+    /// Path to the source file
+    pub path: PathBuf,
+    /// Path of the source file relative to the source directory
+    pub relative_path: PathBuf,
+    /// Path of the output file relative to the output directory
+    pub output: PathBuf,
+    /// URL of the output file, relative to the site root
+    pub url: String,
+    /// Name of the processor, that builds this source, see [`processors::Processors`]
+    pub processor: String,
+}
+
+/// Entry of the site-wide page index, sent to templates as an element of `site.pages`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PageEntry {
+    /// Path of the page source, relative to the source directory
+    pub path: PathBuf,
+    /// URL of the page, relative to the site root
+    pub url: String,
+    /// Page metadata (front matter)
+    pub metadata: markdown::Metadata,
+    /// Summary, word count and reading time
+    #[serde(flatten)]
+    pub info: markdown::ContentInfo,
+}
+
+/// Site-wide information that gets sent to template as `site`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Site {
+    /// Site title, see [`Config::title`]
+    pub title: Option<String>,
+    /// URL the site is hosted at, see [`Config::base_url`]
+    pub base_url: Option<String>,
+    /// Site language, see [`Config::language`]
+    pub language: Option<String>,
+    /// Site description, see [`Config::description`]
+    pub description: Option<String>,
+    /// Custom parameters, see [`Config::params`]
+    pub params: serde_json::Map<String, serde_json::Value>,
+    /// All pages of the site
+    pub pages: Vec<PageEntry>,
+}
+
+impl Site {
+    /// Create site information from config, without any pages
+    pub fn new(config: &Config) -> Self {
+        Self {
+            title: config.title.clone(),
+            base_url: config.base_url.clone(),
+            language: config.language.clone(),
+            description: config.description.clone(),
+            params: config.params.clone(),
+            pages: Vec::new(),
+        }
+    }
+}
+
+/// Check that a relative output path only consists of plain names, so it can't point outside
+/// of the output directory
+fn is_inside_site(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+fn outside_of_site(what: &str, path: &str) -> Diagnostic {
+    Diagnostic::error(
+        diagnostic::Code::Metadata,
+        format!("{} {:?} points outside of the site", what, path),
+    )
+}
+
+impl OreStaty<'_> {
+    /// Find all sources in the given directory, with it's relative path specified.
+    /// Ignored files are skipped, see [`OreStaty::is_ignored`]. Sources of unknown type are
+    /// skipped with a warning
+    pub fn collect_sources(&self, src: &Path, relative_path: &Path) -> Vec<Source> {
+        let files = match std::fs::read_dir(src) {
+            Ok(files) => files,
+            Err(err) => {
+                self.report(Diagnostic::io("Failed to read directory", err).in_file(src));
+                return Vec::new();
+            }
+        };
+
+        let mut sources = Vec::new();
+        for file in files {
+            let file = match file {
+                Ok(file) => file,
+                Err(err) => {
+                    self.report(Diagnostic::io("Failed to read file", err).in_file(src));
+                    continue;
+                }
+            };
+            let relative_path = relative_path.join(file.file_name());
+            if self.is_ignored(&relative_path) {
+                continue;
+            }
+
+            if file.path().is_file() {
+                let Some((processor, extension)) = self.processors.find(&file.path()) else {
+                    self.report(
+                        Diagnostic::warning(
+                            diagnostic::Code::UnknownSource,
+                            "File extension is unknown, skipping; Maybe you wanted to put it into \"static\" directory?",
+                        )
+                        .in_file(&file.path()),
+                    );
+                    continue;
+                };
+                let output = self.output_path(&relative_path, &extension);
+                sources.push(Source {
+                    url: self.output_url(&output),
+                    output,
+                    processor: processor.to_owned(),
+                    path: file.path(),
+                    relative_path,
+                });
+            } else {
+                sources.append(&mut self.collect_sources(&file.path(), &relative_path));
+            }
+        }
+        sources
+    }
+
+    /// Get the path of the output file, relative to the output directory. With
+    /// [`Config::pretty_urls`], HTML pages are put in their own directory, so `about.md` is built
+    /// into `about/index.html`
+    pub fn output_path(&self, relative_path: &Path, extension: &str) -> PathBuf {
+        if self.config.pretty_urls
+            && extension == "html"
+            && relative_path
+                .file_stem()
+                .is_some_and(|stem| stem != "index")
+        {
+            relative_path.with_extension("").join("index.html")
+        } else {
+            relative_path.with_extension(extension)
+        }
+    }
+
+    /// Get the URL of the output file. With [`Config::pretty_urls`], `index.html` is left out,
+    /// so `about/index.html` becomes `/about/`
+    pub fn output_url(&self, output: &Path) -> String {
+        let mut url = String::new();
+        for component in output.components() {
+            url.push('/');
+            url.push_str(&component.as_os_str().to_string_lossy());
+        }
+        if self.config.pretty_urls && output.file_name().is_some_and(|name| name == "index.html") {
+            url.truncate(url.len() - "index.html".len());
+        }
+        url
+    }
+
+    /// Get the output path of a page, set by it's front matter `permalink` or `slug`, or by the
+    /// section pattern in [`Config::permalinks`]. Returns [`None`] if the path is derived from
+    /// the source path as usual.
+    ///
+    /// Permalinks can contain placeholders:
+    /// * `:slug` - front matter `slug`, or the source file name
+    /// * `:title` - page title as a slug, or the source file name
+    /// * `:section` - first directory of the source, relative to "src"
+    /// * `:path` - directory of the source, relative to "src"
+    /// * `:year`, `:month`, `:day` - from the front matter `date`
+    ///
+    /// A permalink ending with `/` is built into `index.html` in that directory. A permalink
+    /// without an extension gets the output extension, following [`Config::pretty_urls`].
+    /// Section patterns don't apply to index pages
+    pub fn permalink(
+        &self,
+        source: &Source,
+        metadata: &markdown::Metadata,
+    ) -> BuildResult<Option<PathBuf>> {
+        let extension = source
+            .output
+            .extension()
+            .map_or(String::new(), |ext| ext.to_string_lossy().into_owned());
+        let directory = source.relative_path.parent().unwrap_or(Path::new(""));
+        let stem = source
+            .relative_path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        let slug = metadata
+            .slug
+            .clone()
+            .unwrap_or_else(|| text::slugify(&stem));
+
+        let section = directory
+            .components()
+            .next()
+            .map_or(String::new(), |section| {
+                section.as_os_str().to_string_lossy().into_owned()
+            });
+        // Section patterns are for pages in the section, so index pages (like the section's
+        // own `blog/index.md`) keep their path
+        let pattern = metadata.permalink.as_ref().or_else(|| {
+            if stem == "index" {
+                return None;
+            }
+            self.config
+                .permalinks
+                .iter()
+                .filter(|(prefix, _)| directory.starts_with(prefix.trim_matches('/')))
+                .max_by_key(|(prefix, _)| prefix.trim_matches('/').len())
+                .map(|(_, pattern)| pattern)
+        });
+        let Some(pattern) = pattern else {
+            let Some(slug) = &metadata.slug else {
+                return Ok(None);
+            };
+            let path = directory.join(format!("{}.{}", slug, extension));
+            if slug.contains(['/', '\\']) || !is_inside_site(&path) {
+                return Err(outside_of_site("Slug", slug));
+            }
+            return Ok(Some(self.output_path(&path, &extension)));
+        };
+
+        let date = metadata.date.as_deref().unwrap_or_default();
+        let mut date_parts = date.split(['-', 'T', ' ']);
+        let (year, month, day) = (date_parts.next(), date_parts.next(), date_parts.next());
+        let mut permalink = String::new();
+        let mut rest = pattern.trim_start_matches('/');
+        while let Some(index) = rest.find(':') {
+            permalink.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+            let end = rest
+                .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+                .unwrap_or(rest.len());
+            let date_part = |part: Option<&str>| {
+                part.filter(|part| !part.is_empty())
+                    .map(str::to_owned)
+                    .ok_or_else(|| {
+                        Diagnostic::error(
+                            diagnostic::Code::Metadata,
+                            format!(
+                                "Permalink {:?} needs a date, but the page has no valid date",
+                                pattern
+                            ),
+                        )
+                    })
+            };
+            match &rest[..end] {
+                "slug" => permalink.push_str(&slug),
+                "title" => {
+                    permalink.push_str(&text::slugify(metadata.title.as_deref().unwrap_or(&stem)))
+                }
+                "section" => permalink.push_str(&section),
+                "path" => permalink.push_str(&directory.to_string_lossy()),
+                "year" => permalink.push_str(&date_part(year)?),
+                "month" => permalink.push_str(&date_part(month)?),
+                "day" => permalink.push_str(&date_part(day)?),
+                placeholder => {
+                    return Err(Diagnostic::error(
+                        diagnostic::Code::Metadata,
+                        format!(
+                            "Unknown placeholder {:?} in permalink {:?}",
+                            placeholder, pattern
+                        ),
+                    ))
+                }
+            }
+            rest = &rest[end..];
+        }
+        permalink.push_str(rest);
+
+        let path = PathBuf::from(permalink.replace("//", "/"));
+        if !is_inside_site(&path) {
+            return Err(outside_of_site("Permalink", &permalink));
+        }
+        Ok(Some(if permalink.ends_with('/') || permalink.is_empty() {
+            path.join("index.html")
+        } else if path.extension().is_some() {
+            path
+        } else {
+            self.output_path(&path, &extension)
+        }))
+    }
+
+    /// Collect page index entry for a source, moving the source to it's permalink (see
+    /// [`Self::permalink`]). Returns [`None`] if the source is not a page.
+    /// Invalid metadata is not reported here, it will be reported when the page is built
+    pub fn index_source(&self, source: &mut Source) -> Option<PageEntry> {
+        if source.output.extension().is_none_or(|ext| ext != "html") {
+            return None;
+        }
+
+        let (metadata, info) = self
+            .processors
+            .get(&source.processor)
+            .and_then(|processor| processor.index(self, source))
+            .unwrap_or_default();
+
+        let permalink = self.permalink(source, &metadata);
+        if let Some(output) = self
+            .unwrap_or_report(permalink.map_err(|diagnostic| diagnostic.in_file(&source.path)))
+            .flatten()
+        {
+            // Permalinks ending with "/" point to the directory, even without pretty URLs
+            let url = self.output_url(&output);
+            source.url = url.strip_suffix("index.html").unwrap_or(&url).to_owned();
+            source.output = output;
+        }
+
+        Some(PageEntry {
+            path: source.relative_path.clone(),
+            url: source.url.clone(),
+            metadata,
+            info,
+        })
+    }
+
+    /// Check if a page should be built: it's not a draft and it's publish date (or date) is
+    /// not in the future, unless [`OreStaty::drafts`] or [`OreStaty::future`] are set.
+    /// Pages with invalid dates are published
+    pub fn is_published(&self, metadata: &markdown::Metadata) -> bool {
+        if metadata.draft && !self.drafts {
+            return false;
+        }
+        let date = metadata.publish_date.as_ref().or(metadata.date.as_ref());
+        self.future
+            || date
+                .and_then(|date| text::parse_date(date))
+                .is_none_or(|date| date <= chrono::Local::now().naive_local())
+    }
+
+    /// Get the site-wide information
+    pub fn site(&self) -> &Site {
+        &self.site
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(permalinks: &[(&str, &str)], pretty_urls: bool) -> OreStaty<'static> {
+        OreStaty::new(
+            Config {
+                permalinks: permalinks
+                    .iter()
+                    .map(|(section, pattern)| (section.to_string(), pattern.to_string()))
+                    .collect(),
+                pretty_urls,
+                ..Config::default()
+            },
+            Path::new(""),
+        )
+    }
+
+    fn source(generator: &OreStaty, relative_path: &str) -> Source {
+        let relative_path = PathBuf::from(relative_path);
+        let output = generator.output_path(&relative_path, "html");
+        Source {
+            path: Path::new("src").join(&relative_path),
+            url: generator.output_url(&output),
+            output,
+            relative_path,
+            processor: "markdown".to_owned(),
+        }
+    }
+
+    fn metadata(front_matter: &str) -> markdown::Metadata {
+        serde_yml::from_str(front_matter).unwrap()
+    }
+
+    #[test]
+    fn no_permalink() {
+        let generator = generator(&[], false);
+        let source = source(&generator, "blog/post.md");
+        assert_eq!(generator.permalink(&source, &metadata("{}")), Ok(None));
+    }
+
+    #[test]
+    fn slug() {
+        let generator = generator(&[], false);
+        let source = source(&generator, "blog/post.md");
+        assert_eq!(
+            generator.permalink(&source, &metadata("slug: hello")),
+            Ok(Some(PathBuf::from("blog/hello.html")))
+        );
+        let generator = self::generator(&[], true);
+        assert_eq!(
+            generator.permalink(&source, &metadata("slug: hello")),
+            Ok(Some(PathBuf::from("blog/hello/index.html")))
+        );
+    }
+
+    #[test]
+    fn slug_outside_of_site() {
+        let generator = generator(&[], false);
+        let source = source(&generator, "blog/post.md");
+        for slug in ["../../escaped", "a/b", "../x", "/etc/passwd", "a\\b"] {
+            let metadata = markdown::Metadata {
+                slug: Some(slug.to_owned()),
+                ..Default::default()
+            };
+            assert!(
+                generator.permalink(&source, &metadata).is_err(),
+                "slug {:?} is accepted",
+                slug
+            );
+        }
+    }
+
+    #[test]
+    fn section_pattern() {
+        let generator = generator(&[("blog", "/blog/:year/:month/:slug/")], false);
+        let source = source(&generator, "blog/post.md");
+        assert_eq!(
+            generator.permalink(&source, &metadata("date: 2024-03-05")),
+            Ok(Some(PathBuf::from("blog/2024/03/post/index.html")))
+        );
+        assert_eq!(
+            generator.permalink(&source, &metadata("{date: 2024-03-05, slug: hi}")),
+            Ok(Some(PathBuf::from("blog/2024/03/hi/index.html")))
+        );
+        assert!(generator.permalink(&source, &metadata("{}")).is_err());
+
+        let index = self::source(&generator, "blog/index.md");
+        assert_eq!(generator.permalink(&index, &metadata("{}")), Ok(None));
+        let index = self::source(&generator, "blog/2024/index.md");
+        assert_eq!(generator.permalink(&index, &metadata("{}")), Ok(None));
+        assert_eq!(
+            generator.permalink(&index, &metadata("permalink: /archive/")),
+            Ok(Some(PathBuf::from("archive/index.html")))
+        );
+    }
+
+    #[test]
+    fn front_matter_permalink() {
+        let generator = generator(&[("blog", "/blog/:slug/")], false);
+        let source = source(&generator, "blog/post.md");
+        assert_eq!(
+            generator.permalink(&source, &metadata("permalink: /legacy/old.html")),
+            Ok(Some(PathBuf::from("legacy/old.html")))
+        );
+        assert_eq!(
+            generator.permalink(
+                &source,
+                &metadata("{permalink: '/:section/:title', title: Hi There}")
+            ),
+            Ok(Some(PathBuf::from("blog/hi-there.html")))
+        );
+        assert!(generator
+            .permalink(&source, &metadata("permalink: /:unknown/"))
+            .is_err());
+    }
+
+    #[test]
+    fn permalink_outside_of_site() {
+        let generator = generator(&[], false);
+        let source = source(&generator, "blog/post.md");
+        for permalink in ["/../escaped.html", "/blog/../../x/", "/:path/../../.."] {
+            let metadata = markdown::Metadata {
+                permalink: Some(permalink.to_owned()),
+                ..Default::default()
+            };
+            assert!(
+                generator.permalink(&source, &metadata).is_err(),
+                "permalink {:?} is accepted",
+                permalink
+            );
+        }
+    }
+}
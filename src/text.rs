@@ -0,0 +1,46 @@
+/// Turn text into a URL-friendly slug: lowercase alphanumeric words, separated by `-`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    if slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+/// Parse a date (`YYYY-MM-DD`) or a date with time (`YYYY-MM-DD HH:MM[:SS]`, `T` can be used
+/// as a separator, RFC 3339 timezone is supported), converting it to local time
+pub fn parse_date(date: &str) -> Option<chrono::NaiveDateTime> {
+    let date = date.trim();
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&chrono::Local).naive_local());
+    }
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| chrono::NaiveDateTime::parse_from_str(date, format).ok())
+    .or_else(|| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(chrono::NaiveTime::MIN))
+    })
+}
+
+/// Convert a value to string, returning [`None`] if formatting fails. Use it to format dates
+/// with user-provided patterns, as `to_string` panics on invalid patterns
+pub fn try_to_string(value: impl std::fmt::Display) -> Option<String> {
+    use std::fmt::Write;
+    let mut string = String::new();
+    write!(string, "{}", value).ok()?;
+    Some(string)
+}
@@ -0,0 +1,111 @@
+use crate::{diagnostic, Diagnostic};
+use notify::Watcher as _;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Files that changed in the project, see [`Watcher::wait`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Changes {
+    /// All changed paths
+    pub paths: Vec<PathBuf>,
+    /// Did any plugin template, helper or style change?
+    pub plugins: bool,
+    /// Did config or ".orestatyignore" change?
+    pub config: bool,
+    /// Did any data file change?
+    pub data: bool,
+    /// Errors, that happened while watching
+    pub errors: Vec<Diagnostic>,
+}
+
+/// Project file watcher. Watches "src", "static", "plugins" and "data" directories,
+/// "config.toml" and ".orestatyignore"
+#[derive(Debug)]
+pub struct Watcher {
+    root: PathBuf,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl Watcher {
+    /// Directories that get watched recursively
+    pub const DIRECTORIES: &'static [&'static str] = &["src", "static", "plugins", "data"];
+    /// Time to wait for more changes after the first one, so that a single save with multiple
+    /// events only triggers one rebuild
+    pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+    /// Start watching project at the given path
+    pub fn new(root: &Path) -> notify::Result<Self> {
+        let root = &root.canonicalize().map_err(notify::Error::io)?;
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(root, notify::RecursiveMode::NonRecursive)?;
+        for directory in Self::DIRECTORIES {
+            let path = root.join(directory);
+            if path.is_dir() {
+                watcher.watch(&path, notify::RecursiveMode::Recursive)?;
+            }
+        }
+
+        Ok(Self {
+            root: root.to_owned(),
+            receiver,
+            _watcher: watcher,
+        })
+    }
+
+    /// Block until something in the project changes, or watching fails (see
+    /// [`Changes::errors`]). Returns [`None`] if the watcher stopped
+    pub fn wait(&self) -> Option<Changes> {
+        let mut changes = Changes::default();
+        while changes.paths.is_empty() && changes.errors.is_empty() {
+            self.collect(self.receiver.recv().ok()?, &mut changes);
+        }
+        while let Ok(event) = self.receiver.recv_timeout(Self::DEBOUNCE) {
+            self.collect(event, &mut changes);
+        }
+        Some(changes)
+    }
+
+    fn collect(&self, event: notify::Result<notify::Event>, changes: &mut Changes) {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                let mut diagnostic =
+                    Diagnostic::error(diagnostic::Code::Io, format!("Watch error: {}", err));
+                if let Some(path) = err.paths.first() {
+                    diagnostic = diagnostic.in_file(path);
+                }
+                changes.errors.push(diagnostic);
+                return;
+            }
+        };
+        if event.kind.is_access() {
+            return;
+        }
+
+        for path in event.paths {
+            let Ok(relative_path) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+            if relative_path == Path::new("config.toml")
+                || relative_path == Path::new(".orestatyignore")
+            {
+                changes.config = true;
+            } else if relative_path.starts_with("plugins") {
+                changes.plugins = true;
+            } else if relative_path.starts_with("data") {
+                changes.data = true;
+            } else if !Self::DIRECTORIES
+                .iter()
+                .any(|directory| relative_path.starts_with(directory))
+            {
+                continue;
+            }
+            if !changes.paths.contains(&path) {
+                changes.paths.push(path);
+            }
+        }
+    }
+}